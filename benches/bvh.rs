@@ -0,0 +1,89 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use riaw::prelude::*;
+use riaw::scenes::random_spheres;
+
+const N_RAYS: usize = 10_000;
+
+type World = Vec<Box<dyn Hittable + Sync>>;
+
+// Camera rays through a grid over the image of the random spheres scene.
+fn sphere_scene() -> (Vec<Ray>, impl Fn() -> World) {
+    let camera = random_spheres::camera().build(2.0);
+    let (width, height) = (200, 50);
+    let mut sampler = IndependentSampler::new();
+    let rays = (0..width * height)
+        .map(|i| {
+            sampler.start_pixel_sample(i as u64, 0);
+            let u = (i % width) as f32 / width as f32;
+            let v = (i / width) as f32 / height as f32;
+            camera.get_ray(u, v, &mut sampler)
+        })
+        .collect();
+    (rays, random_spheres::world)
+}
+
+// Rays from outside a cloud of small random triangles, aimed into it.
+fn triangle_soup() -> (Vec<Ray>, impl Fn() -> World) {
+    let mut rng = seeded_rng(SCENE_STREAM, 1);
+    let mut random_point = move |scale: f32| {
+        scale * vec3![rng.gen::<f32>(), rng.gen::<f32>(), rng.gen::<f32>()] - 0.5 * scale
+    };
+    let mut positions = vec![];
+    for _ in 0..20_000 {
+        let center = random_point(10.0);
+        for _ in 0..3 {
+            positions.push(center + random_point(0.5));
+        }
+    }
+    let rays = (0..N_RAYS)
+        .map(|_| {
+            let origin = vec3![0, 0, 20];
+            Ray::new(origin, random_point(10.0) - origin, 0.0)
+        })
+        .collect();
+    let world = move || {
+        let indices = (0..positions.len() / 3)
+            .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+            .collect();
+        let material = Lambertian::new(vec3![0.5, 0.5, 0.5]);
+        TriangleMesh::new(positions.clone(), indices, material).into_triangles()
+    };
+    (rays, world)
+}
+
+fn count_hits<H: Hittable>(world: &H, rays: &[Ray]) -> usize {
+    rays.iter()
+        .filter(|r| world.hit(r, 1e-3, f32::MAX).is_some())
+        .count()
+}
+
+fn bench_scene(c: &mut Criterion, name: &str, rays: &[Ray], world: impl Fn() -> World) {
+    let median = BuildOptions {
+        split: SplitMethod::Median,
+        ..BuildOptions::default()
+    };
+    let sah = BuildOptions::default();
+
+    let mut group = c.benchmark_group(name);
+    reseed_thread_rng(BVH_STREAM, 0);
+    let tree = BVH::build(world(), 0.0, 1.0, &median);
+    group.bench_function("median tree", |b| b.iter(|| count_hits(&tree, rays)));
+    let tree = BVH::build(world(), 0.0, 1.0, &sah);
+    group.bench_function("sah tree", |b| b.iter(|| count_hits(&tree, rays)));
+    let flat = FlatBVH::new(BVH::build(world(), 0.0, 1.0, &median));
+    group.bench_function("median flat", |b| b.iter(|| count_hits(&flat, rays)));
+    let flat = FlatBVH::new(BVH::build(world(), 0.0, 1.0, &sah));
+    group.bench_function("sah flat", |b| b.iter(|| count_hits(&flat, rays)));
+    group.finish();
+}
+
+fn traversal(c: &mut Criterion) {
+    let (rays, world) = sphere_scene();
+    bench_scene(c, "random_spheres", &rays, world);
+    let (rays, world) = triangle_soup();
+    bench_scene(c, "triangle_soup", &rays, world);
+}
+
+criterion_group!(benches, traversal);
+criterion_main!(benches);
@@ -41,6 +41,36 @@ impl AABB {
         AABB { min, max }
     }
 
+    /// Grow any axis thinner than `delta` to `delta`, so that planar objects
+    /// still present a slab with nonzero thickness to `hit`. Far from the
+    /// origin the padding grows to stay several ulps wide.
+    pub fn padded(self, delta: f32) -> Self {
+        let pad = |min: f32, max: f32| {
+            let delta = delta.max(8.0 * f32::EPSILON * min.abs().max(max.abs()));
+            if max - min < delta {
+                (min - delta / 2.0, max + delta / 2.0)
+            } else {
+                (min, max)
+            }
+        };
+        let (x0, x1) = pad(self.min.x, self.max.x);
+        let (y0, y1) = pad(self.min.y, self.max.y);
+        let (z0, z1) = pad(self.min.z, self.max.z);
+        AABB {
+            min: vec3![x0, y0, z0],
+            max: vec3![x1, y1, z1],
+        }
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
     pub fn hit(&self, r: &Ray, mut tmin: f32, mut tmax: f32) -> bool {
         for a in 0..3 {
             let inv_d = 1.0 / r.direction[a];
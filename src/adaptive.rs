@@ -0,0 +1,114 @@
+//! Adaptive sampling: pixels stop receiving samples once the relative
+//! standard error of their mean luminance is small enough.
+
+use crate::prelude::{vec3, Vec3};
+use crate::tonemap::luminance;
+
+/// When a pixel counts as converged.
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveOptions {
+    /// The relative standard error, of a pixel and of each of its
+    /// neighbours, below which the pixel is no longer sampled.
+    pub threshold: f32,
+    /// Samples every pixel receives before it may be judged, so rare bright
+    /// paths have a chance to show up in the variance.
+    pub min_samples: usize,
+}
+
+impl Default for AdaptiveOptions {
+    fn default() -> Self {
+        AdaptiveOptions {
+            threshold: 0.01,
+            min_samples: 16,
+        }
+    }
+}
+
+/// A running mean and variance of a pixel's sample luminance, by Welford's
+/// method.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PixelStats {
+    pub count: usize,
+    pub mean: f32,
+    m2: f32,
+}
+
+impl PixelStats {
+    pub fn push(&mut self, radiance: Vec3) {
+        let x = luminance(radiance);
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// The unbiased sample variance.
+    pub fn variance(&self) -> f32 {
+        if self.count > 1 {
+            self.m2 / (self.count - 1) as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// The standard error of the mean relative to the mean itself. Pixels
+    /// that have only ever seen black have no error.
+    pub fn relative_error(&self) -> f32 {
+        if self.count == 0 {
+            return f32::INFINITY;
+        }
+        let error = (self.variance() / self.count as f32).sqrt();
+        if error == 0.0 {
+            0.0
+        } else {
+            error / self.mean.abs()
+        }
+    }
+}
+
+/// A color ramp from dark blue through red to yellow for `x` in [0, 1].
+pub fn heat_color(x: f32) -> Vec3 {
+    let x = x.clamp(0.0, 1.0);
+    vec3![
+        (1.5 * x).min(1.0),
+        (2.0 * x - 1.0).clamp(0.0, 1.0),
+        (0.4 - x).max(0.0) + (3.0 * x - 2.0).clamp(0.0, 1.0) * 0.3
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_match_two_pass_variance() {
+        let xs = [0.3f32, 1.7, 0.2, 5.0, 0.9, 0.9, 2.4, 0.05, 3.3, 1.1];
+        let mut stats = PixelStats::default();
+        for &x in xs.iter() {
+            stats.push(vec3![x, x, x]);
+        }
+        let n = xs.len() as f32;
+        let mean = xs.iter().sum::<f32>() / n;
+        let variance = xs.iter().map(|x| (x - mean) * (x - mean)).sum::<f32>() / (n - 1.0);
+        assert_eq!(stats.count, xs.len());
+        assert!((stats.mean - mean).abs() < 1e-5);
+        assert!((stats.variance() - variance).abs() < 1e-5 * variance);
+        let error = (variance / n).sqrt() / mean;
+        assert!((stats.relative_error() - error).abs() < 1e-5 * error);
+    }
+
+    #[test]
+    fn constant_pixels_have_no_error() {
+        let mut stats = PixelStats::default();
+        assert_eq!(stats.relative_error(), f32::INFINITY);
+        for _ in 0..4 {
+            stats.push(vec3![0.2, 0.4, 0.6]);
+        }
+        assert_eq!(stats.variance(), 0.0);
+        assert_eq!(stats.relative_error(), 0.0);
+        let mut black = PixelStats::default();
+        black.push(Vec3::zeros());
+        black.push(Vec3::zeros());
+        assert_eq!(black.relative_error(), 0.0);
+    }
+}
@@ -0,0 +1,106 @@
+//! Arbitrary output variables: per-pixel quantities other than radiance,
+//! recorded where each camera ray first hits the scene, for compositing.
+
+use crate::prelude::{vec3, Vec3};
+use crate::rng::splitmix64;
+
+/// A render pass. Pixels whose rays hit nothing hold zero in every pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Aov {
+    /// The ray parameter `t` of the hit, in all three channels. Camera rays
+    /// reach the distance of focus at `t = 1`.
+    Depth,
+    /// The unit world-space surface normal.
+    Normal,
+    /// The world-space hit point.
+    Position,
+    /// The surface reflectance.
+    Albedo,
+    /// Materials numbered from one in the order they first appear, scanning
+    /// from the top left, in all three channels.
+    MaterialId,
+    /// One more than the index of the primitive in the scene's list of
+    /// objects, in all three channels.
+    ObjectId,
+    /// How far the hit point moves across the image while the shutter is
+    /// open, in pixels, with +y up.
+    Motion,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 7] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Position,
+        Aov::Albedo,
+        Aov::MaterialId,
+        Aov::ObjectId,
+        Aov::Motion,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Position => "position",
+            Aov::Albedo => "albedo",
+            Aov::MaterialId => "material_id",
+            Aov::ObjectId => "object_id",
+            Aov::Motion => "motion",
+        }
+    }
+
+    /// Whether the pass labels pixels, and so takes each pixel's first
+    /// sample rather than averaging them.
+    pub fn is_id(self) -> bool {
+        matches!(self, Aov::MaterialId | Aov::ObjectId)
+    }
+}
+
+/// What a camera ray hits first.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FirstHit {
+    /// The ray parameter of the hit.
+    pub t: f32,
+    /// The distance from the ray's origin to the hit.
+    pub distance: f32,
+    pub position: Vec3,
+    /// The unit surface normal, or zero if the ray hits nothing.
+    pub normal: Vec3,
+    /// The surface reflectance, or the sky's radiance if the ray hits nothing.
+    pub albedo: Vec3,
+    pub velocity: Vec3,
+    /// Distinguishes one material from another, or zero if the ray hits
+    /// nothing.
+    pub material: usize,
+    /// One more than the index of the primitive in the scene's list of
+    /// objects, or zero if the ray hits nothing.
+    pub object: usize,
+}
+
+/// Map a pass into [0, 1] for viewing as an 8-bit image.
+pub fn visualize(aov: Aov, values: &[Vec3]) -> Vec<Vec3> {
+    let max_abs = values
+        .iter()
+        .map(|v| v.x.abs().max(v.y.abs()).max(v.z.abs()))
+        .fold(0.0, f32::max);
+    let scale = if max_abs > 0.0 { 1.0 / max_abs } else { 0.0 };
+    values
+        .iter()
+        .map(|&v| match aov {
+            Aov::Depth | Aov::Albedo => v * scale,
+            Aov::Normal | Aov::Position | Aov::Motion => 0.5 * (v * scale + Vec3::ones()),
+            Aov::MaterialId | Aov::ObjectId => id_color(v.x as u64),
+        })
+        .collect()
+}
+
+// An arbitrary bright color for each id, and black for zero.
+fn id_color(id: u64) -> Vec3 {
+    if id == 0 {
+        return Vec3::zeros();
+    }
+    let h = splitmix64(id);
+    let channel = |shift: u32| 0.2 + 0.8 * ((h >> shift) & 0xff) as f32 / 255.0;
+    vec3![channel(0), channel(8), channel(16)]
+}
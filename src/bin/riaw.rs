@@ -1,81 +1,621 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
-use failure::Error;
+use failure::{format_err, Error};
 use log::info;
-use minifb::{Key, Scale, Window, WindowOptions};
+#[cfg(feature = "viewer")]
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Scale, Window, WindowOptions};
+use structopt::StructOpt;
 
+use riaw::aov::visualize;
+use riaw::output::{is_hdr_path, save_hdr_image, save_image};
 use riaw::prelude::*;
-use riaw::scenes::random_spheres::{skybox, world};
+use riaw::scenes::{self, Scene, Skybox};
 
-const WIDTH: usize = 1200;
-const HEIGHT: usize = 600;
+fn parse_vec3(s: &str) -> Result<Vec3, Error> {
+    let xs = s
+        .split(',')
+        .map(|x| x.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()?;
+    match xs.as_slice() {
+        [x, y, z] => Ok(vec3![*x, *y, *z]),
+        _ => Err(format_err!("expected x,y,z but got '{}'", s)),
+    }
+}
 
-fn main() -> Result<(), Error> {
-    std::env::set_var("RUST_LOG", "info");
-    env_logger::init();
+fn parse_size(s: &str) -> Result<usize, Error> {
+    match s.parse::<usize>()? {
+        0 => Err(format_err!("expected at least one pixel but got '{}'", s)),
+        n => Ok(n),
+    }
+}
+
+fn parse_positive(s: &str) -> Result<f32, Error> {
+    let x = s.parse::<f32>()?;
+    if x > 0.0 {
+        Ok(x)
+    } else {
+        Err(format_err!("expected a positive number but got '{}'", s))
+    }
+}
+
+fn parse_split(s: &str) -> Result<SplitMethod, Error> {
+    match s {
+        "sah" => Ok(SplitMethod::Sah),
+        "median" => Ok(SplitMethod::Median),
+        _ => Err(format_err!("expected sah or median but got '{}'", s)),
+    }
+}
+
+fn parse_filter(s: &str) -> Result<FilterKind, Error> {
+    match s {
+        "box" => Ok(FilterKind::Box),
+        "tent" => Ok(FilterKind::Tent),
+        "gaussian" => Ok(FilterKind::Gaussian),
+        "mitchell" => Ok(FilterKind::Mitchell),
+        "blackman-harris" => Ok(FilterKind::BlackmanHarris),
+        _ => Err(format_err!(
+            "expected box, tent, gaussian, mitchell or blackman-harris but got '{}'",
+            s
+        )),
+    }
+}
+
+fn parse_projection(s: &str) -> Result<Projection, Error> {
+    match s {
+        "perspective" => Ok(Projection::Perspective),
+        "orthographic" => Ok(Projection::Orthographic),
+        "fisheye" => Ok(Projection::Fisheye),
+        "equirectangular" => Ok(Projection::Equirectangular),
+        _ => Err(format_err!(
+            "expected perspective, orthographic, fisheye or equirectangular but got '{}'",
+            s
+        )),
+    }
+}
+
+fn parse_aov(s: &str) -> Result<(Aov, PathBuf), Error> {
+    let (name, path) = s
+        .split_once('=')
+        .ok_or_else(|| format_err!("expected <pass>=<path> but got '{}'", s))?;
+    let names = Aov::ALL.iter().map(|aov| aov.name()).collect::<Vec<_>>();
+    match Aov::ALL.iter().find(|aov| aov.name() == name) {
+        Some(&aov) => Ok((aov, PathBuf::from(path))),
+        None => Err(format_err!(
+            "expected one of {} but got '{}'",
+            names.join(", "),
+            name
+        )),
+    }
+}
+
+const SAMPLERS: &[&str] = &["independent", "stratified", "halton", "sobol"];
+const TONE_MAPS: &[&str] = &["clamp", "reinhard", "extended-reinhard", "aces", "agx"];
+
+#[derive(StructOpt)]
+struct CameraOpts {
+    /// Camera position as x,y,z [default: set by the scene]
+    #[structopt(long, parse(try_from_str = parse_vec3))]
+    look_from: Option<Vec3>,
+    /// Point the camera looks at as x,y,z [default: set by the scene]
+    #[structopt(long, parse(try_from_str = parse_vec3))]
+    look_at: Option<Vec3>,
+    /// Vertical field of view in degrees [default: set by the scene]
+    #[structopt(long)]
+    vfov: Option<f32>,
+    /// Lens aperture; zero is a pinhole [default: set by the scene]
+    #[structopt(long)]
+    aperture: Option<f32>,
+    /// Distance to the plane of focus [default: set by the scene]
+    #[structopt(long)]
+    focus_dist: Option<f32>,
+    /// Projection: perspective, orthographic, fisheye or equirectangular
+    #[structopt(long, default_value = "perspective", parse(try_from_str = parse_projection))]
+    projection: Projection,
+}
+
+impl CameraOpts {
+    fn apply(&self, camera: &mut CameraConfig) {
+        camera.look_from = self.look_from.unwrap_or(camera.look_from);
+        camera.look_at = self.look_at.unwrap_or(camera.look_at);
+        camera.vfov = self.vfov.unwrap_or(camera.vfov);
+        camera.aperture = self.aperture.unwrap_or(camera.aperture);
+        camera.focus_dist = self.focus_dist.unwrap_or(camera.focus_dist);
+    }
+}
+
+#[derive(StructOpt)]
+struct RenderOpts {
+    /// Image width in pixels
+    #[structopt(long, default_value = "1200", parse(try_from_str = parse_size))]
+    width: usize,
+    /// Image height in pixels
+    #[structopt(long, default_value = "600", parse(try_from_str = parse_size))]
+    height: usize,
+    /// Samples per pixel
+    #[structopt(short, long, default_value = "512")]
+    samples: usize,
+    /// Maximum number of bounces per path
+    #[structopt(long, default_value = "50")]
+    max_depth: usize,
+    /// Maximum number of diffuse bounces per path [default: max-depth]
+    #[structopt(long)]
+    max_diffuse: Option<usize>,
+    /// Maximum number of mirror reflections per path [default: max-depth]
+    #[structopt(long)]
+    max_specular: Option<usize>,
+    /// Maximum number of refractions per path [default: max-depth]
+    #[structopt(long)]
+    max_transmission: Option<usize>,
+    /// Bounces before Russian roulette may terminate a path
+    #[structopt(long, default_value = "3")]
+    roulette_depth: usize,
+    /// Source of sample values: independent, stratified, halton or sobol
+    #[structopt(long, default_value = "sobol", possible_values = SAMPLERS)]
+    sampler: String,
+    /// Pixel reconstruction filter: box, tent, gaussian, mitchell or blackman-harris
+    #[structopt(long, default_value = "box", parse(try_from_str = parse_filter))]
+    filter: FilterKind,
+    /// Filter radius in pixels [default: set by the filter]
+    #[structopt(long, parse(try_from_str = parse_positive))]
+    filter_radius: Option<f32>,
+    /// Exposure compensation in stops
+    #[structopt(long, default_value = "0", allow_hyphen_values = true)]
+    exposure: f32,
+    /// Tone-mapping operator: clamp, reinhard, extended-reinhard, aces or agx
+    #[structopt(long, default_value = "clamp", possible_values = TONE_MAPS)]
+    tone_map: String,
+    /// Luminance mapped to white by extended-reinhard
+    #[structopt(long, default_value = "4", parse(try_from_str = parse_positive))]
+    white_point: f32,
+    /// Denoise the image, guided by albedo, normals and depth; toggle with N in the viewer
+    #[structopt(long)]
+    denoise: bool,
+    /// Stop sampling a pixel once the relative standard error of it and its neighbours drops
+    /// below this; heat map of samples with H in the viewer [default: sample every pixel]
+    #[structopt(long)]
+    adaptive_threshold: Option<f32>,
+    /// Samples every pixel receives before adaptive sampling may stop it
+    #[structopt(long, default_value = "16")]
+    min_samples: usize,
+    /// Seed for every random stream
+    #[structopt(long, default_value = "0")]
+    seed: u64,
+    /// Built-in scene name or path to a scene file
+    #[structopt(long, default_value = "random_spheres")]
+    scene: String,
+    /// Replace the scene's sky with a constant color as r,g,b; 0,0,0 leaves emitters as the
+    /// only light
+    #[structopt(long, parse(try_from_str = parse_vec3))]
+    background: Option<Vec3>,
+    /// BVH construction method: sah or median
+    #[structopt(long, default_value = "sah", parse(try_from_str = parse_split))]
+    bvh: SplitMethod,
+    /// Most primitives the SAH builder puts in one BVH leaf
+    #[structopt(long, default_value = "4")]
+    leaf_size: usize,
+    /// Cost of visiting a BVH node relative to intersecting one primitive
+    #[structopt(long, default_value = "0.125")]
+    traversal_cost: f32,
+    #[structopt(flatten)]
+    camera: CameraOpts,
+}
 
-    let mut args = std::env::args();
-    let prog = args.next().unwrap();
-    if args.len() != 0 {
-        println!(
-            "Usage: {}",
-            Path::new(&prog).file_name().unwrap().to_str().unwrap()
+impl RenderOpts {
+    fn scene(&self) -> Result<Scene, Error> {
+        set_seed(self.seed);
+        let mut scene = match scenes::by_name(&self.scene) {
+            Some(scene) => scene,
+            None if Path::new(&self.scene).is_file() => scenes::file::load(&self.scene)?,
+            None => {
+                return Err(format_err!(
+                    "no scene file {}; built-in scenes are {}",
+                    self.scene,
+                    scenes::NAMES.join(", ")
+                ))
+            }
+        };
+        self.camera.apply(&mut scene.camera);
+        if let Some(background) = self.background {
+            scene.skybox = scenes::constant_sky(background);
+        }
+        info!("camera: {:?}", scene.camera);
+        Ok(scene)
+    }
+
+    fn depth_limits(&self) -> DepthLimits {
+        DepthLimits {
+            max: self.max_depth,
+            diffuse: self.max_diffuse.unwrap_or(self.max_depth),
+            specular: self.max_specular.unwrap_or(self.max_depth),
+            transmission: self.max_transmission.unwrap_or(self.max_depth),
+            roulette: self.roulette_depth,
+        }
+    }
+
+    fn sampler(&self) -> Box<dyn Sampler> {
+        match self.sampler.as_str() {
+            "independent" => Box::new(IndependentSampler::new()),
+            "stratified" => Box::new(StratifiedSampler::new(self.samples)),
+            "halton" => Box::new(HaltonSampler::new()),
+            _ => Box::new(SobolSampler::new()),
+        }
+    }
+
+    fn filter(&self) -> Filter {
+        let radius = self
+            .filter_radius
+            .unwrap_or_else(|| self.filter.default_radius());
+        Filter::new(self.filter, radius)
+    }
+
+    fn output_transform(&self) -> OutputTransform {
+        let tone_map = match self.tone_map.as_str() {
+            "reinhard" => ToneMap::Reinhard,
+            "extended-reinhard" => ToneMap::ExtendedReinhard {
+                white: self.white_point,
+            },
+            "aces" => ToneMap::Aces,
+            "agx" => ToneMap::Agx,
+            _ => ToneMap::Clamp,
+        };
+        OutputTransform::new(self.exposure, tone_map)
+    }
+
+    fn adaptive(&self) -> Option<AdaptiveOptions> {
+        self.adaptive_threshold.map(|threshold| AdaptiveOptions {
+            threshold,
+            min_samples: self.min_samples,
+        })
+    }
+
+    fn bvh_options(&self) -> BuildOptions {
+        BuildOptions {
+            split: self.bvh,
+            max_leaf_size: self.leaf_size,
+            traversal_cost: self.traversal_cost,
+            ..BuildOptions::default()
+        }
+    }
+
+    fn tracer(&self, scale: usize) -> Result<Tracer<Skybox, Box<dyn Camera>>, Error> {
+        Ok(self.scene_tracer(self.scene()?, scale))
+    }
+
+    fn scene_tracer(&self, scene: Scene, scale: usize) -> Tracer<Skybox, Box<dyn Camera>> {
+        let camera = scene
+            .camera
+            .build_projection(self.camera.projection, self.aspect());
+        let mut tracer = Tracer::with_bvh_options(
+            camera,
+            scene.world,
+            scene.skybox,
+            self.width * scale,
+            self.height * scale,
+            &self.bvh_options(),
         );
-        return Ok(());
-    }
-
-    let randf = || thread_rng().gen_range(-1f32, 1f32);
-    let look_from = 18.38 * vec3![randf(), randf().abs(), randf()].as_unit();
-    info!("look_from: {:?}", look_from);
-    let look_at = vec3![0, 0, 0];
-    let dist_to_focus = 10.0;
-    let aperature = 0.0;
-    let camera = Camera::new(
-        look_from,
-        look_at,
-        vec3![0, 1, 0],
-        20.0,
-        WIDTH as f32 / HEIGHT as f32,
-        aperature,
-        dist_to_focus,
-        0.0,
-        1.0,
+        tracer.set_depth_limits(self.depth_limits());
+        tracer.set_lights(scene.lights);
+        tracer.set_sampler(self.sampler());
+        tracer.set_filter(self.filter());
+        tracer.set_output_transform(self.output_transform());
+        if self.denoise {
+            tracer.set_denoiser(Some(DenoiseOptions::default()));
+        }
+        tracer.set_adaptive(self.adaptive());
+        tracer
+    }
+
+    fn aspect(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+}
+
+#[derive(StructOpt)]
+#[structopt(about = "A progressive path tracer")]
+enum Command {
+    /// Render a scene to an image file
+    Render {
+        #[structopt(flatten)]
+        opts: RenderOpts,
+        /// Output image (.png, .ppm, .hdr, .pfm or .exr)
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+        /// Also write a render pass, as <pass>=<path>: depth, normal, position, albedo,
+        /// material_id, object_id or motion. HDR formats hold the raw values
+        #[structopt(long = "aov", parse(try_from_str = parse_aov))]
+        aovs: Vec<(Aov, PathBuf)>,
+    },
+    /// Progressively render a scene in a window
+    View {
+        #[structopt(flatten)]
+        opts: RenderOpts,
+    },
+    /// Describe a scene and the render settings
+    Info {
+        #[structopt(flatten)]
+        opts: RenderOpts,
+    },
+    /// Time scene construction and rendering
+    Bench {
+        #[structopt(flatten)]
+        opts: RenderOpts,
+    },
+}
+
+fn render(opts: &RenderOpts, output: &Path, aovs: &[(Aov, PathBuf)]) -> Result<(), Error> {
+    let mut tracer = opts.tracer(1)?;
+    tracer.set_aovs(&aovs.iter().map(|&(aov, _)| aov).collect::<Vec<_>>());
+    let mut n_samples = 0;
+
+    let start = Instant::now();
+    while n_samples < opts.samples && tracer.n_active() > 0 {
+        n_samples = tracer.render_sample();
+        info!(
+            "sample {}/{}, {} pixels active",
+            n_samples,
+            opts.samples,
+            tracer.n_active()
+        );
+    }
+    info!(
+        "rendered {} samples in {:.2}s",
+        n_samples,
+        start.elapsed().as_secs_f32()
     );
-    let scene = world();
-    let sampler = Tracer::new(camera, scene, skybox);
+
+    if is_hdr_path(output) {
+        save_hdr_image(output, &tracer.image(), opts.width, opts.height)?;
+    } else {
+        let mut buffer = vec![0u32; opts.width * opts.height];
+        tracer.to_argb(&mut buffer);
+        save_image(output, &buffer, opts.width, opts.height)?;
+    }
+    info!("wrote {}", output.display());
+
+    for (aov, path) in aovs {
+        let values = tracer.aov(*aov).expect("pass was not recorded");
+        if is_hdr_path(path) {
+            save_hdr_image(path, &values, opts.width, opts.height)?;
+        } else {
+            let buffer = visualize(*aov, &values)
+                .iter()
+                .map(Vec3::to_argb)
+                .collect::<Vec<_>>();
+            save_image(path, &buffer, opts.width, opts.height)?;
+        }
+        info!("wrote {} pass to {}", aov.name(), path.display());
+    }
+    Ok(())
+}
+
+// Radians the camera orbits per pixel the mouse is dragged.
+#[cfg(feature = "viewer")]
+const ORBIT_SPEED: f32 = 0.01;
+// Fraction of the distance to `look_at` flown per frame a key is held.
+#[cfg(feature = "viewer")]
+const FLY_SPEED: f32 = 0.02;
+
+#[cfg(feature = "viewer")]
+const CONTROLS: &str = "left drag: orbit, right drag: pan, scroll: dolly, \
+                        W/A/S/D/R/F: fly, -/=: field of view, ,/.: aperture, \
+                        P: pause, N: denoise, H: heat map, Q: quit";
+
+// Apply the mouse and keyboard input since the last frame to `camera`,
+// returning whether it moved.
+#[cfg(feature = "viewer")]
+fn navigate(
+    window: &Window,
+    camera: &mut CameraConfig,
+    last_mouse: &mut Option<(f32, f32)>,
+) -> bool {
+    let before = *camera;
+    let (_, height) = window.get_size();
+    let key = |k: Key| if window.is_key_down(k) { 1.0 } else { 0.0 };
+
+    let mouse = window.get_mouse_pos(MouseMode::Pass);
+    let orbiting = window.get_mouse_down(MouseButton::Left);
+    let panning =
+        window.get_mouse_down(MouseButton::Right) || window.get_mouse_down(MouseButton::Middle);
+    if let (Some((x, y)), Some((x0, y0))) = (mouse, *last_mouse) {
+        let (dx, dy) = (x - x0, y - y0);
+        if orbiting {
+            camera.orbit(-dx * ORBIT_SPEED, dy * ORBIT_SPEED);
+        } else if panning {
+            // move the scene with the cursor at the distance of `look_at`
+            let view_height = 2.0 * (camera.vfov.to_radians() / 2.0).tan();
+            let scale = view_height / height.max(1) as f32;
+            camera.pan(-dx * scale, dy * scale);
+        }
+    }
+    *last_mouse = if orbiting || panning { mouse } else { None };
+
+    if let Some((_, scroll)) = window.get_scroll_wheel() {
+        camera.dolly(0.9f32.powf(scroll));
+    }
+    camera.fly(
+        FLY_SPEED * (key(Key::W) - key(Key::S)),
+        FLY_SPEED * (key(Key::D) - key(Key::A)),
+        FLY_SPEED * (key(Key::R) - key(Key::F)),
+    );
+
+    if window.is_key_pressed(Key::Minus, KeyRepeat::Yes) {
+        camera.vfov = (camera.vfov * 1.05).min(170.0);
+    }
+    if window.is_key_pressed(Key::Equal, KeyRepeat::Yes) {
+        camera.vfov = (camera.vfov / 1.05).max(1.0);
+    }
+    if window.is_key_pressed(Key::Period, KeyRepeat::Yes) {
+        camera.aperture = (camera.aperture * 1.25).max(0.01);
+    }
+    if window.is_key_pressed(Key::Comma, KeyRepeat::Yes) {
+        camera.aperture /= 1.25;
+        if camera.aperture < 0.01 {
+            camera.aperture = 0.0;
+        }
+    }
+
+    *camera != before
+}
+
+#[cfg(feature = "viewer")]
+fn view(opts: &RenderOpts) -> Result<(), Error> {
+    let (width, height) = (opts.width, opts.height);
+    let scene = opts.scene()?;
+    let mut camera = scene.camera;
+    let mut tracer = opts.scene_tracer(scene, 2);
 
     let mut window = Window::new(
         "riaw - frame 0",
-        WIDTH,
-        HEIGHT,
+        width,
+        height,
         WindowOptions {
             resize: false,
             scale: Scale::HiDPI,
             ..WindowOptions::default()
         },
     )?;
-    let mut buffer = vec![0u32; WIDTH * HEIGHT * 4];
+    info!("{}", CONTROLS);
+    let mut buffer = vec![0u32; width * height * 4];
     let mut n_samples = 0;
+    let mut tracing = true;
+    let mut paused = false;
+    let mut stale = false;
+    let mut heat_map = false;
+    let mut last_mouse = None;
 
-    loop {
-        if !window.is_open()
-            || window.is_key_down(Key::Escape)
-            || window.is_key_down(Key::Q)
-            || window.is_key_down(Key::S)
-            || n_samples >= 512
-        {
+    while window.is_open() && !window.is_key_down(Key::Escape) && !window.is_key_down(Key::Q) {
+        if navigate(&window, &mut camera, &mut last_mouse) {
+            tracer.set_camera(camera.build_projection(opts.camera.projection, opts.aspect()));
+            n_samples = 0;
+            tracing = true;
+        }
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            paused = !paused;
+            info!("tracing {}", if paused { "paused" } else { "resumed" });
+        }
+        if window.is_key_pressed(Key::N, KeyRepeat::No) {
+            let denoiser = match tracer.denoiser() {
+                Some(_) => None,
+                None => Some(DenoiseOptions::default()),
+            };
+            tracer.set_denoiser(denoiser);
+            stale = true;
+        }
+        if window.is_key_pressed(Key::H, KeyRepeat::No) {
+            heat_map = !heat_map;
+            stale = true;
+        }
+        if tracing && (n_samples >= opts.samples || tracer.n_active() == 0) {
             info!("tracing halted");
-            break;
+            tracing = false;
+        }
+        if tracing && !paused {
+            n_samples = tracer.render_sample();
+            stale = true;
+        }
+
+        if stale {
+            if heat_map {
+                tracer.heat_map_argb(&mut buffer);
+            } else {
+                tracer.to_argb(&mut buffer);
+            }
+            window.update_with_buffer(buffer.as_ref())?;
+            let denoised = if tracer.denoiser().is_some() {
+                " (denoised)"
+            } else {
+                ""
+            };
+            window.set_title(format!("riaw - frame {}{}", n_samples, denoised).as_str());
+            stale = false;
+        } else {
+            std::thread::sleep(std::time::Duration::from_millis(30));
+            window.update();
         }
-        n_samples = sampler.render_sample(&mut buffer, WIDTH * 2, HEIGHT * 2, n_samples);
-        window.update_with_buffer(buffer.as_ref())?;
-        window.set_title(format!("riaw - frame {}", n_samples).as_str());
     }
 
-    while window.is_open() && !window.is_key_down(Key::Escape) && !window.is_key_down(Key::Q) {
-        std::thread::sleep(std::time::Duration::from_millis(100));
-        window.update();
+    Ok(())
+}
+
+#[cfg(not(feature = "viewer"))]
+fn view(_opts: &RenderOpts) -> Result<(), Error> {
+    failure::bail!("riaw was built without the viewer feature; use the render subcommand")
+}
+
+fn info(opts: &RenderOpts) -> Result<(), Error> {
+    let scene = opts.scene()?;
+    let n_objects = scene.world.len();
+    let camera = scene.camera;
+    reseed_thread_rng(BVH_STREAM, 0);
+    let bvh = BVH::build(scene.world, camera.time0, camera.time1, &opts.bvh_options());
+    println!("scene:      {}", opts.scene);
+    println!("objects:    {}", n_objects);
+    if let Some(bounds) = bvh.bounding_box(0.0, 0.0) {
+        println!("bounds:     {:?} - {:?}", bounds.min, bounds.max);
     }
+    println!(
+        "bvh:        {:?}, SAH cost {:.2}",
+        opts.bvh,
+        bvh.sah_cost(opts.traversal_cost)
+    );
+    println!("look_from:  {:?}", camera.look_from);
+    println!("look_at:    {:?}", camera.look_at);
+    println!("projection: {:?}", opts.camera.projection);
+    println!("vfov:       {}", camera.vfov);
+    println!("aperture:   {}", camera.aperture);
+    println!("focus_dist: {}", camera.focus_dist);
+    println!("resolution: {}x{}", opts.width, opts.height);
+    println!("samples:    {}", opts.samples);
+    println!("depth:      {:?}", opts.depth_limits());
+    println!("sampler:    {}", opts.sampler);
+    println!("filter:     {:?}", opts.filter());
+    println!("output:     {:?}", opts.output_transform());
+    println!("denoise:    {}", opts.denoise);
+    println!("adaptive:   {:?}", opts.adaptive());
+    println!("seed:       {}", opts.seed);
+    Ok(())
+}
+
+fn bench(opts: &RenderOpts) -> Result<(), Error> {
+    let start = Instant::now();
+    let mut tracer = opts.tracer(1)?;
+    let build = start.elapsed().as_secs_f32();
+
+    let start = Instant::now();
+    for _ in 0..opts.samples {
+        tracer.render_sample();
+    }
+    let render = start.elapsed().as_secs_f32();
+    let pixel_samples = (opts.width * opts.height * opts.samples) as f32;
 
+    println!("build:  {:.3}s", build);
+    println!(
+        "bvh:    {:?}, SAH cost {:.2}",
+        opts.bvh,
+        tracer.world().sah_cost(opts.traversal_cost)
+    );
+    println!(
+        "render: {:.3}s for {} samples ({:.1}ms/sample)",
+        render,
+        opts.samples,
+        1e3 * render / opts.samples.max(1) as f32
+    );
+    println!(
+        "speed:  {:.3} Msamples/s",
+        pixel_samples / render.max(f32::EPSILON) / 1e6
+    );
     Ok(())
 }
+
+fn main() -> Result<(), Error> {
+    std::env::set_var("RUST_LOG", "info");
+    env_logger::init();
+
+    match Command::from_args() {
+        Command::Render { opts, output, aovs } => render(&opts, &output, &aovs),
+        Command::View { opts } => view(&opts),
+        Command::Info { opts } => info(&opts),
+        Command::Bench { opts } => bench(&opts),
+    }
+}
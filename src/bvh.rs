@@ -1,68 +1,240 @@
-use crate::prelude::{thread_rng, HitRecord, Hittable, Ray, Rng, AABB};
+use crate::prelude::{thread_rng, HitRecord, Hittable, Ray, Rng, Vec3, AABB};
+
+/// How `BVH::build` partitions primitives between the two children of a node.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplitMethod {
+    /// Minimize the surface area heuristic over binned candidate planes on all
+    /// three axes.
+    Sah,
+    /// Split at the median along a random axis, one primitive per leaf.
+    Median,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BuildOptions {
+    pub split: SplitMethod,
+    /// Nodes with more primitives than this are always split.
+    pub max_leaf_size: usize,
+    /// The cost of visiting an interior node, relative to one primitive
+    /// intersection.
+    pub traversal_cost: f32,
+    /// Candidate split planes per axis.
+    pub bins: usize,
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        BuildOptions {
+            split: SplitMethod::Sah,
+            max_leaf_size: 4,
+            traversal_cost: 0.125,
+            bins: 16,
+        }
+    }
+}
 
 pub enum BVH {
     Node {
-        left: Box<dyn Hittable + Sync>,
-        right: Box<dyn Hittable + Sync>,
+        left: Box<BVH>,
+        right: Box<BVH>,
         bbox: AABB,
     },
     Leaf {
-        obj: Box<dyn Hittable + Sync>,
+        objs: Vec<Box<dyn Hittable + Sync>>,
+        // the position of each in the list the tree was built from
+        ids: Vec<usize>,
         bbox: AABB,
     },
 }
 
-macro_rules! box_compare {
-    ($name:ident, $ax:ident) => {
-        fn $name(
-            lhs: &Box<dyn Hittable + Sync>,
-            rhs: &Box<dyn Hittable + Sync>,
-        ) -> std::cmp::Ordering {
-            let box_lhs = lhs.bounding_box(0.0, 0.0).unwrap();
-            let box_rhs = rhs.bounding_box(0.0, 0.0).unwrap();
-            box_lhs
-                .min
-                .$ax
-                .partial_cmp(&box_rhs.min.$ax)
-                .expect("NaN encountered in BVH construction")
-        }
-    };
+// A primitive with its bounds cached for the builder.
+struct Item {
+    obj: Box<dyn Hittable + Sync>,
+    index: usize,
+    bbox: AABB,
+    centroid: Vec3,
 }
 
-box_compare!(box_compare_x, x);
-box_compare!(box_compare_y, y);
-box_compare!(box_compare_z, z);
+fn enclose<'a, I: Iterator<Item = &'a AABB>>(mut boxes: I) -> AABB {
+    let first = *boxes.next().expect("BVH of no objects");
+    boxes.fold(first, |acc, &b| AABB::surrounding_box(acc, b))
+}
 
 impl BVH {
-    pub fn new(mut objs: Vec<Box<dyn Hittable + Sync>>, t0: f32, t1: f32) -> Self {
-        match thread_rng().gen_range(0usize, 3) {
-            0 => objs.sort_by(box_compare_x),
-            1 => objs.sort_by(box_compare_y),
-            2 => objs.sort_by(box_compare_z),
-            _ => panic!("unreachable"),
+    /// A tree built with the default `BuildOptions`.
+    pub fn new(objs: Vec<Box<dyn Hittable + Sync>>, t0: f32, t1: f32) -> Self {
+        BVH::build(objs, t0, t1, &BuildOptions::default())
+    }
+
+    pub fn build(
+        objs: Vec<Box<dyn Hittable + Sync>>,
+        t0: f32,
+        t1: f32,
+        options: &BuildOptions,
+    ) -> Self {
+        let items = objs
+            .into_iter()
+            .enumerate()
+            .map(|(index, obj)| {
+                let bbox = obj
+                    .bounding_box(t0, t1)
+                    .expect("object without a bounding box in BVH");
+                Item {
+                    obj,
+                    index,
+                    bbox,
+                    centroid: bbox.centroid(),
+                }
+            })
+            .collect();
+        match options.split {
+            SplitMethod::Sah => BVH::build_sah(items, options),
+            SplitMethod::Median => BVH::build_median(items),
+        }
+    }
+
+    fn leaf(items: Vec<Item>) -> Self {
+        let bbox = enclose(items.iter().map(|item| &item.bbox));
+        let (objs, ids) = items.into_iter().map(|item| (item.obj, item.index)).unzip();
+        BVH::Leaf { objs, ids, bbox }
+    }
+
+    fn node(left: BVH, right: BVH) -> Self {
+        let bbox = AABB::surrounding_box(left.bbox(), right.bbox());
+        BVH::Node {
+            left: Box::new(left),
+            right: Box::new(right),
+            bbox,
+        }
+    }
+
+    fn build_median(mut items: Vec<Item>) -> Self {
+        if items.len() == 1 {
+            return BVH::leaf(items);
+        }
+        let axis = thread_rng().gen_range(0usize, 3);
+        items.sort_by(|a, b| {
+            a.bbox.min[axis]
+                .partial_cmp(&b.bbox.min[axis])
+                .expect("NaN encountered in BVH construction")
+        });
+        let right = items.split_off(items.len() / 2);
+        BVH::node(BVH::build_median(items), BVH::build_median(right))
+    }
+
+    fn build_sah(items: Vec<Item>, options: &BuildOptions) -> Self {
+        let n = items.len();
+        if n == 1 {
+            return BVH::leaf(items);
+        }
+        let bbox = enclose(items.iter().map(|item| &item.bbox));
+        let centroids = items
+            .iter()
+            .map(|item| AABB::new(item.centroid, item.centroid))
+            .collect::<Vec<_>>();
+        let bounds = enclose(centroids.iter());
+        let n_bins = options.bins.max(2);
+        let bin = |item: &Item, axis: usize| {
+            let extent = bounds.max[axis] - bounds.min[axis];
+            let b = ((item.centroid[axis] - bounds.min[axis]) / extent * n_bins as f32) as usize;
+            b.min(n_bins - 1)
         };
 
-        if objs.len() == 1 {
-            let obj = objs.pop().unwrap();
-            let bbox = obj.bounding_box(t0, t1).unwrap();
-            BVH::Leaf { obj, bbox }
-        } else if objs.len() == 2 {
-            let right = objs.pop().unwrap();
-            let left = objs.pop().unwrap();
-            let bbox = AABB::surrounding_box(
-                left.bounding_box(t0, t1).unwrap(),
-                right.bounding_box(t0, t1).unwrap(),
-            );
-            BVH::Node { left, right, bbox }
-        } else {
-            let right =
-                BVH::new(objs.drain(objs.len() / 2..).collect::<Vec<_>>(), t0, t1).into_box();
-            let left = BVH::new(objs, t0, t1).into_box();
-            let bbox = AABB::surrounding_box(
-                left.bounding_box(t0, t1).unwrap(),
-                right.bounding_box(t0, t1).unwrap(),
-            );
-            BVH::Node { left, right, bbox }
+        // the cheapest (cost, axis, last bin on the left) over every axis
+        let mut best: Option<(f32, usize, usize)> = None;
+        for axis in 0..3 {
+            if bounds.max[axis] - bounds.min[axis] <= 0.0 {
+                continue;
+            }
+            let mut counts = vec![0usize; n_bins];
+            let mut boxes: Vec<Option<AABB>> = vec![None; n_bins];
+            for item in items.iter() {
+                let b = bin(item, axis);
+                counts[b] += 1;
+                boxes[b] = Some(match boxes[b] {
+                    Some(acc) => AABB::surrounding_box(acc, item.bbox),
+                    None => item.bbox,
+                });
+            }
+            // sweep from the right to get the area and count beyond each plane
+            let mut right_area = vec![0.0; n_bins];
+            let mut right_count = vec![0usize; n_bins];
+            let mut acc: Option<AABB> = None;
+            let mut count = 0;
+            for b in (1..n_bins).rev() {
+                if let Some(bb) = boxes[b] {
+                    acc = Some(acc.map_or(bb, |a| AABB::surrounding_box(a, bb)));
+                }
+                count += counts[b];
+                right_area[b - 1] = acc.map_or(0.0, |a| a.surface_area());
+                right_count[b - 1] = count;
+            }
+            let mut acc: Option<AABB> = None;
+            let mut count = 0;
+            for b in 0..n_bins - 1 {
+                if let Some(bb) = boxes[b] {
+                    acc = Some(acc.map_or(bb, |a| AABB::surrounding_box(a, bb)));
+                }
+                count += counts[b];
+                if count == 0 || right_count[b] == 0 {
+                    continue;
+                }
+                let left_area = acc.map_or(0.0, |a| a.surface_area());
+                let cost = options.traversal_cost
+                    + (count as f32 * left_area + right_count[b] as f32 * right_area[b])
+                        / bbox.surface_area();
+                if best.map_or(true, |(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, b));
+                }
+            }
+        }
+
+        let leaf_cost = n as f32;
+        match best {
+            Some((cost, _, _)) if n <= options.max_leaf_size && leaf_cost <= cost => {
+                BVH::leaf(items)
+            }
+            Some((_, axis, split)) => {
+                let (left, right) = items.into_iter().partition(|item| bin(item, axis) <= split);
+                BVH::node(
+                    BVH::build_sah(left, options),
+                    BVH::build_sah(right, options),
+                )
+            }
+            // every centroid coincides, so no plane separates them
+            None if n <= options.max_leaf_size => BVH::leaf(items),
+            None => {
+                let mut left = items;
+                let right = left.split_off(n / 2);
+                BVH::node(
+                    BVH::build_sah(left, options),
+                    BVH::build_sah(right, options),
+                )
+            }
+        }
+    }
+
+    fn bbox(&self) -> AABB {
+        match self {
+            BVH::Node { bbox, .. } => *bbox,
+            BVH::Leaf { bbox, .. } => *bbox,
+        }
+    }
+
+    /// The expected cost of tracing a random ray through the tree, counting
+    /// each node visit as `traversal_cost` and each primitive test as one.
+    pub fn sah_cost(&self, traversal_cost: f32) -> f32 {
+        self.weighted_cost(traversal_cost) / self.bbox().surface_area()
+    }
+
+    fn weighted_cost(&self, traversal_cost: f32) -> f32 {
+        match self {
+            BVH::Node { left, right, bbox } => {
+                traversal_cost * bbox.surface_area()
+                    + left.weighted_cost(traversal_cost)
+                    + right.weighted_cost(traversal_cost)
+            }
+            BVH::Leaf { objs, bbox, .. } => objs.len() as f32 * bbox.surface_area(),
         }
     }
 }
@@ -70,12 +242,20 @@ impl BVH {
 impl Hittable for BVH {
     fn hit(&self, r: &Ray, tmin: f32, tmax: f32) -> Option<HitRecord<'_>> {
         match self {
-            BVH::Leaf { obj, bbox } => {
-                if bbox.hit(r, tmin, tmax) {
-                    obj.hit(r, tmin, tmax)
-                } else {
-                    None
+            BVH::Leaf { objs, ids, bbox } => {
+                if !bbox.hit(r, tmin, tmax) {
+                    return None;
                 }
+                let mut closest = tmax;
+                let mut result = None;
+                for (obj, &id) in objs.iter().zip(ids.iter()) {
+                    if let Some(mut hit) = obj.hit(r, tmin, closest) {
+                        closest = hit.t;
+                        hit.object_id = id;
+                        result = Some(hit);
+                    }
+                }
+                result
             }
             BVH::Node { left, right, bbox } => {
                 if bbox.hit(r, tmin, tmax) {
@@ -101,13 +281,316 @@ impl Hittable for BVH {
     }
 
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
-        match self {
-            BVH::Node { bbox, .. } => Some(*bbox),
-            BVH::Leaf { bbox, .. } => Some(*bbox),
+        Some(self.bbox())
+    }
+
+    fn into_box(self) -> Box<dyn Hittable + Sync> {
+        Box::new(self) as Box<dyn Hittable + Sync>
+    }
+}
+
+/// One node of a `FlatBVH`.
+///
+/// The first child of an interior node immediately follows it, and `offset`
+/// is the index of the second. For a leaf, `offset` is the first of its
+/// `count` primitives.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct FlatNode {
+    bbox: AABB,
+    offset: u32,
+    count: u16,
+    axis: u8,
+}
+
+const _: () = assert!(std::mem::size_of::<FlatNode>() == 32);
+
+// Trees up to this deep are traversed with a stack on the call stack; deeper
+// ones, which only degenerate layouts produce, allocate one per ray.
+const INLINE_STACK: usize = 64;
+
+/// A `BVH` laid out depth-first in one array, traversed without recursion.
+pub struct FlatBVH {
+    nodes: Vec<FlatNode>,
+    objs: Vec<Box<dyn Hittable + Sync>>,
+    // the position of each primitive in the list the tree was built from
+    ids: Vec<usize>,
+    // levels in the deepest path from the root to a leaf
+    depth: usize,
+}
+
+impl FlatBVH {
+    pub fn new(bvh: BVH) -> Self {
+        let mut flat = FlatBVH {
+            nodes: vec![],
+            objs: vec![],
+            ids: vec![],
+            depth: 0,
+        };
+        flat.flatten(bvh, 1);
+        flat
+    }
+
+    // append the subtree in depth-first order, returning its index
+    fn flatten(&mut self, bvh: BVH, depth: usize) -> usize {
+        self.depth = self.depth.max(depth);
+        let index = self.nodes.len();
+        match bvh {
+            BVH::Leaf { objs, ids, bbox } => {
+                assert!(objs.len() <= u16::MAX as usize, "BVH leaf too large");
+                self.nodes.push(FlatNode {
+                    bbox,
+                    offset: self.objs.len() as u32,
+                    count: objs.len() as u16,
+                    axis: 0,
+                });
+                self.objs.extend(objs);
+                self.ids.extend(ids);
+            }
+            BVH::Node { left, right, bbox } => {
+                // order children along the axis separating them the most
+                let d = right.bbox().centroid() - left.bbox().centroid();
+                let axis = (0..3)
+                    .max_by(|&a, &b| d[a].abs().partial_cmp(&d[b].abs()).unwrap())
+                    .unwrap();
+                let (first, second) = if d[axis] < 0.0 {
+                    (right, left)
+                } else {
+                    (left, right)
+                };
+                self.nodes.push(FlatNode {
+                    bbox,
+                    offset: 0,
+                    count: 0,
+                    axis: axis as u8,
+                });
+                self.flatten(*first, depth + 1);
+                let offset = self.flatten(*second, depth + 1);
+                self.nodes[index].offset = offset as u32;
+            }
+        }
+        index
+    }
+
+    pub fn n_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Levels in the deepest path from the root to a leaf.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// See `BVH::sah_cost`.
+    pub fn sah_cost(&self, traversal_cost: f32) -> f32 {
+        let weighted: f32 = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let cost = if node.count > 0 {
+                    node.count as f32
+                } else {
+                    traversal_cost
+                };
+                cost * node.bbox.surface_area()
+            })
+            .sum();
+        weighted / self.nodes[0].bbox.surface_area()
+    }
+}
+
+// The slab test against `bbox` with the ray's reciprocal direction precomputed.
+fn slab_hit(bbox: &AABB, origin: Vec3, inv_dir: Vec3, mut tmin: f32, mut tmax: f32) -> bool {
+    for a in 0..3 {
+        let mut t0 = (bbox.min[a] - origin[a]) * inv_dir[a];
+        let mut t1 = (bbox.max[a] - origin[a]) * inv_dir[a];
+        if inv_dir[a] < 0.0 {
+            std::mem::swap(&mut t0, &mut t1);
+        }
+        tmin = if t0 > tmin { t0 } else { tmin };
+        tmax = if t1 < tmax { t1 } else { tmax };
+        if tmax <= tmin {
+            return false;
         }
     }
+    true
+}
+
+impl Hittable for FlatBVH {
+    fn hit(&self, r: &Ray, tmin: f32, mut tmax: f32) -> Option<HitRecord<'_>> {
+        let inv_dir = 1.0 / r.direction;
+        // every level above a leaf defers at most one node
+        let mut inline = [0usize; INLINE_STACK];
+        let mut heap;
+        let stack: &mut [usize] = if self.depth <= INLINE_STACK {
+            &mut inline
+        } else {
+            heap = vec![0; self.depth];
+            &mut heap
+        };
+        let mut top = 0;
+        let mut current = 0;
+        let mut result = None;
+        loop {
+            let node = &self.nodes[current];
+            if slab_hit(&node.bbox, r.origin, inv_dir, tmin, tmax) {
+                if node.count > 0 {
+                    let first = node.offset as usize;
+                    let objs = &self.objs[first..first + node.count as usize];
+                    for (i, obj) in objs.iter().enumerate() {
+                        if let Some(mut hit) = obj.hit(r, tmin, tmax) {
+                            tmax = hit.t;
+                            hit.object_id = self.ids[first + i];
+                            result = Some(hit);
+                        }
+                    }
+                } else {
+                    // visit the nearer child first, deferring the other
+                    let (near, far) = if inv_dir[node.axis as usize] < 0.0 {
+                        (node.offset as usize, current + 1)
+                    } else {
+                        (current + 1, node.offset as usize)
+                    };
+                    stack[top] = far;
+                    top += 1;
+                    current = near;
+                    continue;
+                }
+            }
+            if top == 0 {
+                break;
+            }
+            top -= 1;
+            current = stack[top];
+        }
+        result
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        Some(self.nodes[0].bbox)
+    }
 
     fn into_box(self) -> Box<dyn Hittable + Sync> {
         Box::new(self) as Box<dyn Hittable + Sync>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{seeded_rng, vec3, Lambertian, Sphere, SCENE_STREAM};
+
+    fn sphere(center: Vec3, radius: f32) -> Box<dyn Hittable + Sync> {
+        Sphere::new(center, radius, Lambertian::new(Vec3::ones())).into_box()
+    }
+
+    fn random_spheres(n: usize) -> Vec<Box<dyn Hittable + Sync>> {
+        let mut rng = seeded_rng(SCENE_STREAM, 1);
+        (0..n)
+            .map(|_| {
+                let center = vec3![
+                    rng.gen_range(-10.0, 10.0),
+                    rng.gen_range(-10.0, 10.0),
+                    rng.gen_range(-10.0, 10.0)
+                ];
+                sphere(center, rng.gen_range(0.1, 1.0))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flat_hits_match_tree_hits() {
+        let options = [
+            BuildOptions::default(),
+            BuildOptions {
+                split: SplitMethod::Median,
+                ..BuildOptions::default()
+            },
+        ];
+        let mut rng = seeded_rng(SCENE_STREAM, 2);
+        for options in options.iter() {
+            let tree = BVH::build(random_spheres(200), 0.0, 1.0, options);
+            let flat = FlatBVH::new(BVH::build(random_spheres(200), 0.0, 1.0, options));
+            let mut hits = 0;
+            for _ in 0..2000 {
+                let origin = vec3![
+                    rng.gen_range(-15.0, 15.0),
+                    rng.gen_range(-15.0, 15.0),
+                    rng.gen_range(-15.0, 15.0)
+                ];
+                let direction = vec3![
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0)
+                ];
+                let r = Ray::new(origin, direction, 0.0);
+                let expected = tree
+                    .hit(&r, 1e-3, f32::MAX)
+                    .map(|hit| (hit.t, hit.p, hit.object_id));
+                let actual = flat
+                    .hit(&r, 1e-3, f32::MAX)
+                    .map(|hit| (hit.t, hit.p, hit.object_id));
+                assert_eq!(expected, actual);
+                hits += expected.is_some() as usize;
+            }
+            assert!(hits > 100);
+        }
+    }
+
+    #[test]
+    fn object_ids_are_input_positions() {
+        let builds = [
+            BuildOptions::default(),
+            BuildOptions {
+                bins: 3,
+                max_leaf_size: 8,
+                ..BuildOptions::default()
+            },
+            BuildOptions {
+                split: SplitMethod::Median,
+                ..BuildOptions::default()
+            },
+        ];
+        // a shuffled row of spheres, so input order differs from the tree's
+        let xs = [7, 2, 9, 0, 4, 11, 5, 1, 8, 3, 10, 6];
+        for options in builds.iter() {
+            let objs = xs
+                .iter()
+                .map(|&x| sphere(vec3![x as f32 * 3.0, 0, 0], 1.0))
+                .collect();
+            let flat = FlatBVH::new(BVH::build(objs, 0.0, 1.0, options));
+            for (id, &x) in xs.iter().enumerate() {
+                let r = Ray::new(vec3![x as f32 * 3.0, 0, 10], vec3![0, 0, -1], 0.0);
+                let hit = flat.hit(&r, 1e-3, f32::MAX).expect("missed a sphere");
+                assert_eq!(hit.object_id, id);
+            }
+        }
+    }
+
+    // `x` along one of the coordinate axes
+    fn on_axis(axis: usize, x: f32) -> Vec3 {
+        [vec3![x, 0, 0], vec3![0, x, 0], vec3![0, 0, x]][axis]
+    }
+
+    #[test]
+    fn degenerate_trees_deeper_than_the_inline_stack() {
+        // each SAH split peels a single sphere off the far end of an axis
+        let mut objs = vec![];
+        for k in 0..40 {
+            let x = 1e20 * 17f32.powi(-k);
+            for axis in 0..3 {
+                objs.push(sphere(on_axis(axis, x), x * 1e-3));
+            }
+        }
+        let flat = FlatBVH::new(BVH::new(objs, 0.0, 1.0));
+        assert!(flat.depth() > INLINE_STACK);
+        // a sphere of ordinary size, far down the tree
+        let x = 1e20 * 17f32.powi(-16);
+        for axis in 0..3 {
+            let r = Ray::new(on_axis(axis, 2.0 * x), on_axis(axis, -1.0), 0.0);
+            let hit = flat.hit(&r, 1e-3, f32::MAX).expect("missed the sphere");
+            let expected = on_axis(axis, x * (1.0 + 1e-3));
+            assert!((hit.p - expected).len() < 1e-5 * x);
+        }
+    }
+}
@@ -1,6 +1,70 @@
-use crate::prelude::{random_in_unit_disk, thread_rng, Ray, Rng, Vec3};
+use std::f32::consts::PI;
 
-pub struct Camera {
+use crate::prelude::{random_in_unit_disk, vec3, Ray, Sampler, Vec3};
+
+/// Maps points on the film to rays into the scene.
+///
+/// Film coordinates `(s, t)` run from zero to one, from the bottom left.
+/// Rays reach the distance of focus at `t = 1`.
+pub trait Camera: Sync {
+    /// The ray through `(s, t)`, drawing a point on the lens, if any, and a
+    /// time within the shutter interval from `sampler`.
+    fn get_ray(&self, s: f32, t: f32, sampler: &mut dyn Sampler) -> Ray;
+
+    /// The film coordinates a point is seen at, ignoring the lens, or `None`
+    /// if the camera can't see it.
+    fn project(&self, p: Vec3) -> Option<(f32, f32)>;
+
+    /// When the shutter opens and closes.
+    fn shutter(&self) -> (f32, f32);
+}
+
+impl<C: Camera + ?Sized> Camera for Box<C> {
+    fn get_ray(&self, s: f32, t: f32, sampler: &mut dyn Sampler) -> Ray {
+        (**self).get_ray(s, t, sampler)
+    }
+
+    fn project(&self, p: Vec3) -> Option<(f32, f32)> {
+        (**self).project(p)
+    }
+
+    fn shutter(&self) -> (f32, f32) {
+        (**self).shutter()
+    }
+}
+
+fn sample_time(time0: f32, time1: f32, sampler: &mut dyn Sampler) -> f32 {
+    time0 + sampler.get_1d() * (time1 - time0)
+}
+
+// `vup`, or if the camera looks along it, the coordinate axis most nearly
+// across the view instead, so the camera still has a well-defined roll.
+fn steady_up(forward: Vec3, vup: Vec3) -> Vec3 {
+    let forward = forward.as_unit();
+    if forward.cross(&vup.as_unit()).squared_len() > 1e-8 {
+        return vup;
+    }
+    [vec3![0, 0, -1], vec3![1, 0, 0], vec3![0, 1, 0]]
+        .iter()
+        .copied()
+        .min_by(|a, b| {
+            let (da, db) = (a.dot(&forward).abs(), b.dot(&forward).abs());
+            da.partial_cmp(&db).unwrap()
+        })
+        .unwrap()
+}
+
+// The camera's right, up and backward axes.
+fn camera_axes(look_from: Vec3, look_at: Vec3, vup: Vec3) -> (Vec3, Vec3, Vec3) {
+    let w = (look_from - look_at).as_unit();
+    let u = steady_up(-w, vup).cross(&w).as_unit();
+    let v = w.cross(&u);
+    (u, v, w)
+}
+
+/// A perspective camera with a thin lens, which blurs what lies off the
+/// plane of focus.
+pub struct ThinLensCamera {
     pub origin: Vec3,
     pub lower_left: Vec3,
     pub horizontal: Vec3,
@@ -13,7 +77,7 @@ pub struct Camera {
     pub time1: f32,
 }
 
-impl Camera {
+impl ThinLensCamera {
     /*
     pub fn default() -> Self {
         let origin = Vec3::zeros();
@@ -29,6 +93,7 @@ impl Camera {
     }
     */
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         look_from: Vec3,
         look_at: Vec3,
@@ -45,14 +110,12 @@ impl Camera {
         let half_height = (theta / 2.0).tan();
         let half_width = aspect * half_height;
         let origin = look_from;
-        let w = (look_from - look_at).as_unit();
-        let u = vup.cross(&w).as_unit();
-        let v = w.cross(&u);
+        let (u, v, w) = camera_axes(look_from, look_at, vup);
         let lower_left =
             origin - half_width * focus_dist * u - half_height * focus_dist * v - focus_dist * w;
         let horizontal = 2.0 * half_width * focus_dist * u;
         let vertical = 2.0 * half_height * focus_dist * v;
-        Camera {
+        ThinLensCamera {
             origin,
             lower_left,
             horizontal,
@@ -65,15 +128,445 @@ impl Camera {
             time1,
         }
     }
+}
 
-    pub fn get_ray(&self, s: f32, t: f32) -> Ray {
-        let rd = self.lens_radius * random_in_unit_disk();
+impl Camera for ThinLensCamera {
+    fn get_ray(&self, s: f32, t: f32, sampler: &mut dyn Sampler) -> Ray {
+        let rd = self.lens_radius * random_in_unit_disk(sampler);
         let offset = self.u * rd.x + self.v * rd.y;
-        let time = thread_rng().gen_range(self.time0, self.time1);
+        let time = sample_time(self.time0, self.time1, sampler);
         Ray::new(
             self.origin + offset,
             self.lower_left + s * self.horizontal + t * self.vertical - self.origin - offset,
             time,
         )
     }
+
+    fn project(&self, p: Vec3) -> Option<(f32, f32)> {
+        let d = p - self.origin;
+        let depth = -d.dot(&self.w);
+        if depth <= 0.0 {
+            return None;
+        }
+        // scale onto the plane of focus, which the image plane lies in
+        let focus_dist = -(self.lower_left - self.origin).dot(&self.w);
+        let q = self.origin + d * (focus_dist / depth) - self.lower_left;
+        Some((
+            q.dot(&self.horizontal) / self.horizontal.squared_len(),
+            q.dot(&self.vertical) / self.vertical.squared_len(),
+        ))
+    }
+
+    fn shutter(&self) -> (f32, f32) {
+        (self.time0, self.time1)
+    }
+}
+
+/// Parallel rays from a rectangle facing `look_at`, so objects keep their
+/// size however far away they are.
+pub struct OrthographicCamera {
+    pub lower_left: Vec3,
+    pub horizontal: Vec3,
+    pub vertical: Vec3,
+    // from the film to the plane of focus
+    pub direction: Vec3,
+    pub time0: f32,
+    pub time1: f32,
+}
+
+impl OrthographicCamera {
+    /// A camera seeing a region `height` tall, and `aspect` times as wide.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        look_from: Vec3,
+        look_at: Vec3,
+        vup: Vec3,
+        height: f32,
+        aspect: f32,
+        focus_dist: f32,
+        time0: f32,
+        time1: f32,
+    ) -> Self {
+        let (u, v, w) = camera_axes(look_from, look_at, vup);
+        let horizontal = aspect * height * u;
+        let vertical = height * v;
+        OrthographicCamera {
+            lower_left: look_from - 0.5 * horizontal - 0.5 * vertical,
+            horizontal,
+            vertical,
+            direction: -focus_dist * w,
+            time0,
+            time1,
+        }
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn get_ray(&self, s: f32, t: f32, sampler: &mut dyn Sampler) -> Ray {
+        let time = sample_time(self.time0, self.time1, sampler);
+        Ray::new(
+            self.lower_left + s * self.horizontal + t * self.vertical,
+            self.direction,
+            time,
+        )
+    }
+
+    fn project(&self, p: Vec3) -> Option<(f32, f32)> {
+        let q = p - self.lower_left;
+        if q.dot(&self.direction) <= 0.0 {
+            return None;
+        }
+        Some((
+            q.dot(&self.horizontal) / self.horizontal.squared_len(),
+            q.dot(&self.vertical) / self.vertical.squared_len(),
+        ))
+    }
+
+    fn shutter(&self) -> (f32, f32) {
+        (self.time0, self.time1)
+    }
+}
+
+/// An equidistant fisheye: the angle from the view direction grows in
+/// proportion to the distance from the center of the image, up to looking
+/// straight back.
+pub struct FisheyeCamera {
+    pub origin: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+    pub aspect: f32,
+    // the angle at the top and bottom edges of the image, in radians
+    pub half_fov: f32,
+    pub focus_dist: f32,
+    pub time0: f32,
+    pub time1: f32,
+}
+
+impl FisheyeCamera {
+    /// A camera seeing `vfov` degrees from the bottom edge of the image to
+    /// the top.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        look_from: Vec3,
+        look_at: Vec3,
+        vup: Vec3,
+        vfov: f32,
+        aspect: f32,
+        focus_dist: f32,
+        time0: f32,
+        time1: f32,
+    ) -> Self {
+        let (u, v, w) = camera_axes(look_from, look_at, vup);
+        FisheyeCamera {
+            origin: look_from,
+            u,
+            v,
+            w,
+            aspect,
+            half_fov: vfov.to_radians() / 2.0,
+            focus_dist,
+            time0,
+            time1,
+        }
+    }
+}
+
+impl Camera for FisheyeCamera {
+    fn get_ray(&self, s: f32, t: f32, sampler: &mut dyn Sampler) -> Ray {
+        let x = (2.0 * s - 1.0) * self.aspect;
+        let y = 2.0 * t - 1.0;
+        let theta = ((x * x + y * y).sqrt() * self.half_fov).min(PI);
+        let phi = y.atan2(x);
+        let direction =
+            theta.sin() * (phi.cos() * self.u + phi.sin() * self.v) - theta.cos() * self.w;
+        let time = sample_time(self.time0, self.time1, sampler);
+        Ray::new(self.origin, self.focus_dist * direction, time)
+    }
+
+    fn project(&self, p: Vec3) -> Option<(f32, f32)> {
+        let d = p - self.origin;
+        let len = d.len();
+        if len == 0.0 {
+            return None;
+        }
+        let theta = (-d.dot(&self.w) / len).clamp(-1.0, 1.0).acos();
+        let phi = d.dot(&self.v).atan2(d.dot(&self.u));
+        let r = theta / self.half_fov;
+        Some((
+            (r * phi.cos() / self.aspect + 1.0) / 2.0,
+            (r * phi.sin() + 1.0) / 2.0,
+        ))
+    }
+
+    fn shutter(&self) -> (f32, f32) {
+        (self.time0, self.time1)
+    }
+}
+
+/// A 360° panorama, with longitude across the image and latitude up it. The
+/// image should be twice as wide as it is tall.
+pub struct EquirectangularCamera {
+    pub origin: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+    pub focus_dist: f32,
+    pub time0: f32,
+    pub time1: f32,
+}
+
+impl EquirectangularCamera {
+    /// A panorama around `look_from`, with `look_at` in the middle.
+    pub fn new(
+        look_from: Vec3,
+        look_at: Vec3,
+        vup: Vec3,
+        focus_dist: f32,
+        time0: f32,
+        time1: f32,
+    ) -> Self {
+        let (u, v, w) = camera_axes(look_from, look_at, vup);
+        EquirectangularCamera {
+            origin: look_from,
+            u,
+            v,
+            w,
+            focus_dist,
+            time0,
+            time1,
+        }
+    }
+}
+
+impl Camera for EquirectangularCamera {
+    fn get_ray(&self, s: f32, t: f32, sampler: &mut dyn Sampler) -> Ray {
+        let longitude = (s - 0.5) * 2.0 * PI;
+        let latitude = (t - 0.5) * PI;
+        let direction = latitude.cos() * (longitude.sin() * self.u - longitude.cos() * self.w)
+            + latitude.sin() * self.v;
+        let time = sample_time(self.time0, self.time1, sampler);
+        Ray::new(self.origin, self.focus_dist * direction, time)
+    }
+
+    fn project(&self, p: Vec3) -> Option<(f32, f32)> {
+        let d = p - self.origin;
+        let len = d.len();
+        if len == 0.0 {
+            return None;
+        }
+        let longitude = d.dot(&self.u).atan2(-d.dot(&self.w));
+        let latitude = (d.dot(&self.v) / len).clamp(-1.0, 1.0).asin();
+        Some((longitude / (2.0 * PI) + 0.5, latitude / PI + 0.5))
+    }
+
+    fn shutter(&self) -> (f32, f32) {
+        (self.time0, self.time1)
+    }
+}
+
+/// The kinds of `Camera` a `CameraConfig` can build.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective,
+    Orthographic,
+    Fisheye,
+    Equirectangular,
+}
+
+/// The resolution-independent parameters of a `Camera`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraConfig {
+    pub look_from: Vec3,
+    pub look_at: Vec3,
+    pub vup: Vec3,
+    pub vfov: f32,
+    pub aperture: f32,
+    pub focus_dist: f32,
+    pub time0: f32,
+    pub time1: f32,
+}
+
+impl CameraConfig {
+    // The direction the camera faces, and unit vectors to its right and up.
+    fn basis(&self) -> (Vec3, Vec3, Vec3) {
+        let forward = (self.look_at - self.look_from).as_unit();
+        let right = forward.cross(&steady_up(forward, self.vup)).as_unit();
+        let up = right.cross(&forward);
+        (forward, right, up)
+    }
+
+    /// Swing the camera around `look_at`, by `yaw` radians about `vup` and
+    /// `pitch` radians towards it. The camera never passes over the pole.
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        let offset = self.look_from - self.look_at;
+        let up = self.vup.as_unit();
+        let (_, right, _) = self.basis();
+        let back = right.cross(&up);
+        let (x, y, z) = (offset.dot(&right), offset.dot(&up), offset.dot(&back));
+        let r = offset.len();
+        let azimuth = x.atan2(z) + yaw;
+        let polar = (y / r).acos().clamp(0.01, PI - 0.01) - pitch;
+        let polar = polar.clamp(0.01, PI - 0.01);
+        let (sin_p, cos_p) = polar.sin_cos();
+        let (sin_a, cos_a) = azimuth.sin_cos();
+        self.look_from =
+            self.look_at + r * (sin_p * sin_a * right + cos_p * up + sin_p * cos_a * back);
+    }
+
+    /// Slide the camera and the point it looks at across the view, by
+    /// fractions of the distance between them.
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        let (_, right, up) = self.basis();
+        let distance = (self.look_at - self.look_from).len();
+        let offset = distance * (dx * right + dy * up);
+        self.look_from += offset;
+        self.look_at += offset;
+    }
+
+    /// Move the camera towards `look_at`, scaling the distance between them
+    /// and to the plane of focus by `factor`.
+    pub fn dolly(&mut self, factor: f32) {
+        self.look_from = self.look_at + factor * (self.look_from - self.look_at);
+        self.focus_dist *= factor;
+    }
+
+    /// Move the camera and the point it looks at together, forwards, to the
+    /// right and up, by fractions of the distance between them.
+    pub fn fly(&mut self, forward: f32, right: f32, up: f32) {
+        let (f, r, u) = self.basis();
+        let distance = (self.look_at - self.look_from).len();
+        let offset = distance * (forward * f + right * r + up * u);
+        self.look_from += offset;
+        self.look_at += offset;
+    }
+
+    /// A thin-lens perspective camera for an image `aspect` times as wide as
+    /// it is tall.
+    pub fn build(&self, aspect: f32) -> ThinLensCamera {
+        ThinLensCamera::new(
+            self.look_from,
+            self.look_at,
+            self.vup,
+            self.vfov,
+            aspect,
+            self.aperture,
+            self.focus_dist,
+            self.time0,
+            self.time1,
+        )
+    }
+
+    /// A camera of any projection. The orthographic camera sees as much of
+    /// the plane of focus as the perspective one, and only the perspective
+    /// camera has a lens.
+    pub fn build_projection(&self, projection: Projection, aspect: f32) -> Box<dyn Camera> {
+        match projection {
+            Projection::Perspective => Box::new(self.build(aspect)),
+            Projection::Orthographic => Box::new(OrthographicCamera::new(
+                self.look_from,
+                self.look_at,
+                self.vup,
+                2.0 * self.focus_dist * (self.vfov.to_radians() / 2.0).tan(),
+                aspect,
+                self.focus_dist,
+                self.time0,
+                self.time1,
+            )),
+            Projection::Fisheye => Box::new(FisheyeCamera::new(
+                self.look_from,
+                self.look_at,
+                self.vup,
+                self.vfov,
+                aspect,
+                self.focus_dist,
+                self.time0,
+                self.time1,
+            )),
+            Projection::Equirectangular => Box::new(EquirectangularCamera::new(
+                self.look_from,
+                self.look_at,
+                self.vup,
+                self.focus_dist,
+                self.time0,
+                self.time1,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::IndependentSampler;
+
+    fn config(look_from: Vec3, look_at: Vec3) -> CameraConfig {
+        CameraConfig {
+            look_from,
+            look_at,
+            vup: vec3![0, 1, 0],
+            vfov: 40.0,
+            aperture: 0.0,
+            focus_dist: (look_at - look_from).len(),
+            time0: 0.0,
+            time1: 0.0,
+        }
+    }
+
+    fn is_finite(v: Vec3) -> bool {
+        v.x.is_finite() && v.y.is_finite() && v.z.is_finite()
+    }
+
+    #[test]
+    fn looking_along_vup_stays_finite() {
+        let mut camera = config(vec3![0, 5, 0], Vec3::zeros());
+        let (forward, right, up) = camera.basis();
+        assert!(is_finite(right) && is_finite(up));
+        assert!(right.dot(&forward).abs() < 1e-6 && up.dot(&forward).abs() < 1e-6);
+
+        let ray = camera
+            .build(1.0)
+            .get_ray(0.25, 0.75, &mut IndependentSampler::new());
+        assert!(is_finite(ray.direction));
+
+        camera.pan(0.1, 0.2);
+        camera.fly(0.1, 0.2, 0.3);
+        assert!(is_finite(camera.look_from) && is_finite(camera.look_at));
+        camera.orbit(0.3, -0.2);
+        assert!(is_finite(camera.look_from));
+        assert!((camera.look_from - camera.look_at).len() > 4.9);
+    }
+
+    #[test]
+    fn project_inverts_get_ray() {
+        let config = config(vec3![1, 2, 3], vec3![-1, 0.5, -2]);
+        let projections = [
+            Projection::Perspective,
+            Projection::Orthographic,
+            Projection::Fisheye,
+            Projection::Equirectangular,
+        ];
+        let mut sampler = IndependentSampler::new();
+        for &projection in projections.iter() {
+            let camera = config.build_projection(projection, 1.5);
+            // away from the edges, and the poles of the panorama
+            for &s in [0.1, 0.3, 0.5, 0.7, 0.9].iter() {
+                for &t in [0.2, 0.4, 0.5, 0.6, 0.8].iter() {
+                    let ray = camera.get_ray(s, t, &mut sampler);
+                    for &distance in [0.5, 1.0, 3.0].iter() {
+                        let (ps, pt) = camera.project(ray.point_at(distance)).unwrap();
+                        assert!(
+                            (ps - s).abs() < 1e-4 && (pt - t).abs() < 1e-4,
+                            "{:?} at ({}, {}) projects to ({}, {})",
+                            projection,
+                            s,
+                            t,
+                            ps,
+                            pt
+                        );
+                    }
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,208 @@
+//! An edge-avoiding à-trous wavelet denoiser, after Dammertz et al., "Edge-
+//! Avoiding À-Trous Wavelet Transform for fast Global Illumination
+//! Filtering" (2010).
+//!
+//! Each pass blurs with a 5×5 B3-spline kernel whose taps are spread twice
+//! as far apart as in the pass before, so a few passes cover a wide area.
+//! Taps are weighted down where the color, albedo, normal or depth differ
+//! from the pixel being filtered, which keeps edges and textures sharp.
+//! Texture is divided out before filtering and multiplied back in after, so
+//! only the lighting is blurred.
+
+use rayon::prelude::*;
+
+use crate::prelude::{vec3, Vec3};
+
+/// What a camera ray first sees, averaged over a pixel's samples.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Features {
+    /// Reflectance at the first hit, or the sky's radiance if there is none.
+    pub albedo: Vec3,
+    /// Unit world-space normal at the first hit, or zero if there is none.
+    pub normal: Vec3,
+    /// Distance from the camera to the first hit, or zero if there is none.
+    pub depth: f32,
+}
+
+/// How strongly the denoiser smooths, and what it treats as an edge.
+#[derive(Clone, Copy, Debug)]
+pub struct DenoiseOptions {
+    /// Filter passes; the last spans `4 << iterations` pixels.
+    pub iterations: usize,
+    /// Tolerated difference in tone-compressed lighting, halved every pass.
+    pub sigma_color: f32,
+    /// Tolerated distance between unit normals.
+    pub sigma_normal: f32,
+    /// Tolerated difference in albedo.
+    pub sigma_albedo: f32,
+    /// Tolerated depth difference, relative to the depth.
+    pub sigma_depth: f32,
+}
+
+impl Default for DenoiseOptions {
+    fn default() -> Self {
+        DenoiseOptions {
+            iterations: 5,
+            sigma_color: 1.0,
+            sigma_normal: 0.3,
+            sigma_albedo: 0.1,
+            sigma_depth: 0.1,
+        }
+    }
+}
+
+const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+// Albedo below this is treated as this, so black surfaces still carry
+// their lighting through the filter.
+const MIN_ALBEDO: f32 = 1e-2;
+
+fn demodulator(albedo: Vec3) -> Vec3 {
+    vec3![
+        albedo.x.max(MIN_ALBEDO),
+        albedo.y.max(MIN_ALBEDO),
+        albedo.z.max(MIN_ALBEDO)
+    ]
+}
+
+// Bounds lighting to [0, 1) per channel, so bright emitters don't make
+// every neighbour look like an edge.
+fn compress(v: Vec3) -> Vec3 {
+    vec3![v.x / (1.0 + v.x), v.y / (1.0 + v.y), v.z / (1.0 + v.z)]
+}
+
+/// Denoise `color`, stored top row first, using the `features` of the same
+/// pixels as a guide.
+pub fn denoise(
+    color: &[Vec3],
+    features: &[Features],
+    width: usize,
+    height: usize,
+    options: &DenoiseOptions,
+) -> Vec<Vec3> {
+    if color.len() < width * height || features.len() < width * height {
+        panic!("denoising buffer is insufficiently sized");
+    }
+    let mut lighting: Vec<Vec3> = color[..width * height]
+        .par_iter()
+        .zip(features.par_iter())
+        .map(|(&c, f)| c / demodulator(f.albedo))
+        .collect();
+    let mut filtered = vec![Vec3::zeros(); width * height];
+
+    for pass in 0..options.iterations {
+        let step = 1 << pass;
+        let sigma_color = options.sigma_color / (1 << pass) as f32;
+        let src = &lighting;
+        filtered
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, out) in row.iter_mut().enumerate() {
+                    let p = y * width + x;
+                    let (cp, fp) = (compress(src[p]), &features[p]);
+                    let mut sum = Vec3::zeros();
+                    let mut weights = 0.0;
+                    for (j, ky) in KERNEL.iter().enumerate() {
+                        let qy = y as isize + (j as isize - 2) * step;
+                        if qy < 0 || qy >= height as isize {
+                            continue;
+                        }
+                        for (i, kx) in KERNEL.iter().enumerate() {
+                            let qx = x as isize + (i as isize - 2) * step;
+                            if qx < 0 || qx >= width as isize {
+                                continue;
+                            }
+                            let q = qy as usize * width + qx as usize;
+                            let fq = &features[q];
+                            let dc = (cp - compress(src[q])).squared_len();
+                            let dn = (fp.normal - fq.normal).squared_len();
+                            let da = (fp.albedo - fq.albedo).squared_len();
+                            let dz = (fp.depth - fq.depth)
+                                / (options.sigma_depth * fp.depth.max(fq.depth)).max(1e-6);
+                            let w = kx
+                                * ky
+                                * (-dc / (sigma_color * sigma_color)
+                                    - dn / (options.sigma_normal * options.sigma_normal)
+                                    - da / (options.sigma_albedo * options.sigma_albedo)
+                                    - dz * dz)
+                                    .exp();
+                            sum += w * src[q];
+                            weights += w;
+                        }
+                    }
+                    // the center tap always has weight, so `weights` is positive
+                    *out = sum / weights;
+                }
+            });
+        std::mem::swap(&mut lighting, &mut filtered);
+    }
+
+    lighting
+        .par_iter()
+        .zip(features.par_iter())
+        .map(|(&l, f)| l * demodulator(f.albedo))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &[Vec3], b: &[Vec3]) {
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert!(
+                (*a - *b).len() < 1e-5 * b.len().max(1.0),
+                "{:?} != {:?}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn constant_images_are_fixed_points() {
+        let (width, height) = (37, 23);
+        let features = Features {
+            albedo: vec3![0.7, 0.5, 0.2],
+            normal: vec3![0, 1, 0],
+            depth: 3.0,
+        };
+        for &c in [vec3![0.35, 0.25, 0.1], vec3![12.0, 4.0, 0.5]].iter() {
+            let color = vec![c; width * height];
+            let denoised = denoise(
+                &color,
+                &vec![features; width * height],
+                width,
+                height,
+                &DenoiseOptions::default(),
+            );
+            assert_close(&denoised, &color);
+        }
+    }
+
+    #[test]
+    fn edges_between_surfaces_are_kept() {
+        // two walls at right angles, lit differently, meeting down the middle
+        let (width, height) = (32, 16);
+        let mut color = vec![];
+        let mut features = vec![];
+        for _ in 0..height {
+            for x in 0..width {
+                let left = x < width / 2;
+                color.push(if left {
+                    vec3![0.8, 0.8, 0.8]
+                } else {
+                    vec3![0.1, 0.1, 0.1]
+                });
+                features.push(Features {
+                    albedo: vec3![0.5, 0.5, 0.5],
+                    normal: if left { vec3![1, 0, 0] } else { vec3![0, 0, 1] },
+                    depth: 2.0,
+                });
+            }
+        }
+        let denoised = denoise(&color, &features, width, height, &DenoiseOptions::default());
+        assert_close(&denoised, &color);
+    }
+}
@@ -0,0 +1,182 @@
+//! Pixel reconstruction filters.
+//!
+//! Every camera sample contributes to each pixel whose center lies within the
+//! filter's radius, weighted by the filter at the offset between the two. A
+//! pixel's value is the weighted mean of the samples around it.
+
+use std::f32::consts::PI;
+
+/// The shape of a reconstruction filter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterKind {
+    /// Equal weight everywhere within the radius.
+    Box,
+    /// Falls off linearly to zero at the radius.
+    Tent,
+    /// A Gaussian with standard deviation a third of the radius, shifted down
+    /// to reach zero at the radius.
+    Gaussian,
+    /// The Mitchell-Netravali cubic with B = C = 1/3. Its negative lobes
+    /// sharpen edges, but can ring around bright ones.
+    Mitchell,
+    /// The four-term Blackman-Harris window.
+    BlackmanHarris,
+}
+
+impl FilterKind {
+    /// The radius, in pixels, the filter is usually used with.
+    pub fn default_radius(self) -> f32 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.0,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell | FilterKind::BlackmanHarris => 2.0,
+        }
+    }
+}
+
+/// A separable filter over offsets in pixels, normalized to integrate to one.
+#[derive(Clone, Copy, Debug)]
+pub struct Filter {
+    pub kind: FilterKind,
+    pub radius: f32,
+}
+
+impl Filter {
+    /// A filter reaching `radius` pixels from the pixel center.
+    ///
+    /// # Panics
+    ///
+    /// If `radius` is not positive: a filter that covers no pixel centers
+    /// would weight every sample zero.
+    pub fn new(kind: FilterKind, radius: f32) -> Self {
+        assert!(radius > 0.0, "filter radius must be positive");
+        Filter { kind, radius }
+    }
+
+    /// The weight of a sample offset by `(dx, dy)` from a pixel center.
+    pub fn evaluate(&self, dx: f32, dy: f32) -> f32 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    pub fn evaluate_1d(&self, x: f32) -> f32 {
+        self.profile(x) / self.integral_1d()
+    }
+
+    // The filter's shape, unnormalized.
+    fn profile(&self, x: f32) -> f32 {
+        let r = self.radius;
+        let x = x.abs();
+        if x > r {
+            return 0.0;
+        }
+        match self.kind {
+            FilterKind::Box => 1.0,
+            FilterKind::Tent => r - x,
+            FilterKind::Gaussian => {
+                let sigma = r / 3.0;
+                let g = |x: f32| (-x * x / (2.0 * sigma * sigma)).exp();
+                (g(x) - g(r)).max(0.0)
+            }
+            FilterKind::Mitchell => mitchell(2.0 * x / r, 1.0 / 3.0, 1.0 / 3.0),
+            FilterKind::BlackmanHarris => {
+                let t = 2.0 * PI * (x + r) / (2.0 * r);
+                0.35875 - 0.48829 * t.cos() + 0.14128 * (2.0 * t).cos() - 0.01168 * (3.0 * t).cos()
+            }
+        }
+    }
+}
+
+impl Filter {
+    // The integral of `profile` over [-radius, radius].
+    fn integral_1d(&self) -> f32 {
+        let r = self.radius;
+        match self.kind {
+            FilterKind::Box => 2.0 * r,
+            FilterKind::Tent => r * r,
+            // sigma sqrt(2 pi) erf(3 / sqrt(2)) - 2 r exp(-4.5), with sigma = r / 3
+            FilterKind::Gaussian => 0.811_069 * r,
+            // the cubic integrates to one over [-2, 2]
+            FilterKind::Mitchell => 0.5 * r,
+            // the window's mean is its constant term
+            FilterKind::BlackmanHarris => 2.0 * 0.35875 * r,
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::new(FilterKind::Box, FilterKind::Box.default_radius())
+    }
+}
+
+// The Mitchell-Netravali cubic over [0, 2].
+fn mitchell(x: f32, b: f32, c: f32) -> f32 {
+    let (x2, x3) = (x * x, x * x * x);
+    let w = if x < 1.0 {
+        (12.0 - 9.0 * b - 6.0 * c) * x3 + (-18.0 + 12.0 * b + 6.0 * c) * x2 + (6.0 - 2.0 * b)
+    } else if x < 2.0 {
+        (-b - 6.0 * c) * x3
+            + (6.0 * b + 30.0 * c) * x2
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c)
+    } else {
+        0.0
+    };
+    w / 6.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [FilterKind; 5] = [
+        FilterKind::Box,
+        FilterKind::Tent,
+        FilterKind::Gaussian,
+        FilterKind::Mitchell,
+        FilterKind::BlackmanHarris,
+    ];
+
+    #[test]
+    fn filters_integrate_to_one() {
+        for &kind in KINDS.iter() {
+            for &radius in [kind.default_radius(), 1.3].iter() {
+                let filter = Filter::new(kind, radius);
+                // the midpoint rule, in one and two dimensions
+                let n = 400;
+                let dx = 2.0 * radius / n as f32;
+                let xs = (0..n)
+                    .map(|i| -radius + (i as f32 + 0.5) * dx)
+                    .collect::<Vec<_>>();
+                let integral_1d: f32 = xs.iter().map(|&x| filter.evaluate_1d(x) * dx).sum();
+                let integral_2d: f32 = xs
+                    .iter()
+                    .flat_map(|&x| xs.iter().map(move |&y| filter.evaluate(x, y) * dx * dx))
+                    .sum();
+                assert!(
+                    (integral_1d - 1.0).abs() < 1e-3,
+                    "{:?} {}",
+                    kind,
+                    integral_1d
+                );
+                assert!(
+                    (integral_2d - 1.0).abs() < 1e-3,
+                    "{:?} {}",
+                    kind,
+                    integral_2d
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn filters_vanish_beyond_their_radius() {
+        for &kind in KINDS.iter() {
+            let filter = Filter::new(kind, 1.5);
+            assert_eq!(filter.evaluate_1d(1.51), 0.0);
+            assert_eq!(filter.evaluate_1d(-1.51), 0.0);
+            assert!(filter.evaluate_1d(0.0) > 0.0);
+        }
+    }
+}
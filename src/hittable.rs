@@ -1,10 +1,16 @@
-use crate::prelude::{Material, Ray, Vec3, AABB};
+use crate::prelude::{vec3, Material, Ray, Sampler, Vec3, AABB};
 
 pub struct HitRecord<'a> {
     pub t: f32,
     pub p: Vec3,
     pub normal: Vec3,
+    pub u: f32,
+    pub v: f32,
     pub material: &'a dyn Material,
+    /// How fast the surface moves, in world units per unit of time.
+    pub velocity: Vec3,
+    /// The index of the primitive hit in the list its `BVH` was built from.
+    pub object_id: usize,
 }
 
 impl<'a> HitRecord<'a> {
@@ -13,15 +19,43 @@ impl<'a> HitRecord<'a> {
             t,
             p,
             normal,
+            u: 0.0,
+            v: 0.0,
             material,
+            velocity: Vec3::zeros(),
+            object_id: 0,
         }
     }
+
+    /// Set the surface coordinates used for texture lookups.
+    pub fn with_uv(mut self, u: f32, v: f32) -> Self {
+        self.u = u;
+        self.v = v;
+        self
+    }
+
+    /// Set the velocity of a moving surface.
+    pub fn with_velocity(mut self, velocity: Vec3) -> Self {
+        self.velocity = velocity;
+        self
+    }
 }
 
 pub trait Hittable {
     fn hit(&self, r: &Ray, tmin: f32, tmax: f32) -> Option<HitRecord<'_>>;
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB>;
     fn into_box(self) -> Box<dyn Hittable + Sync>;
+
+    /// The solid-angle density with which `random` picks `direction` from
+    /// `origin`; zero for shapes that cannot be sampled as lights.
+    fn pdf_value(&self, _origin: Vec3, _direction: Vec3) -> f32 {
+        0.0
+    }
+
+    /// A direction from `origin` towards a random point on the shape.
+    fn random(&self, _origin: Vec3, _sampler: &mut dyn Sampler) -> Vec3 {
+        vec3![1, 0, 0]
+    }
 }
 
 impl Hittable for Vec<Box<dyn Hittable + Sync>> {
@@ -43,4 +77,18 @@ impl Hittable for Vec<Box<dyn Hittable + Sync>> {
     fn into_box(self) -> Box<dyn Hittable + Sync> {
         Box::new(self) as Box<dyn Hittable + Sync>
     }
+
+    // an equal mixture of every member
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let sum: f32 = self.iter().map(|h| h.pdf_value(origin, direction)).sum();
+        sum / self.len() as f32
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let i = ((sampler.get_1d() * self.len() as f32) as usize).min(self.len() - 1);
+        self[i].random(origin, sampler)
+    }
 }
@@ -1,37 +1,42 @@
 mod aabb;
+pub mod adaptive;
+pub mod aov;
 mod bvh;
 mod camera;
+pub mod denoise;
+mod filter;
 mod hittable;
 mod material;
+mod matrix;
+mod medium;
+pub mod obj;
+pub mod output;
+mod pdf;
+mod quad;
 mod ray;
 mod rng;
+pub mod sampler;
 mod sphere;
+mod texture;
+pub mod tonemap;
 mod tracer;
+mod transform;
+mod triangle;
 mod vec3;
 
 pub mod scenes;
 
-use rng::Rng;
+use sampler::{concentric_disk, uniform_sphere, Sampler};
 use vec3::Vec3;
 
-pub fn random_in_unit_disk() -> Vec3 {
-    let mut rng = rng::thread_rng();
-    loop {
-        let p = 2.0 * vec3![rng.gen::<f32>(), rng.gen::<f32>(), 0] - vec3![1, 1, 0];
-        if p.squared_len() < 1.0 {
-            return p;
-        }
-    }
+pub fn random_in_unit_disk(sampler: &mut dyn Sampler) -> Vec3 {
+    concentric_disk(sampler.get_2d())
 }
 
-pub fn random_in_unit_sphere() -> Vec3 {
-    let mut rng = rng::thread_rng();
-    loop {
-        let p = 2.0 * vec3![rng.gen::<f32>(), rng.gen::<f32>(), rng.gen::<f32>()] - Vec3::ones();
-        if p.squared_len() < 1.0 {
-            return p;
-        }
-    }
+pub fn random_in_unit_sphere(sampler: &mut dyn Sampler) -> Vec3 {
+    // the radius has density proportional to r²
+    let r = sampler.get_1d().cbrt();
+    r * uniform_sphere(sampler.get_2d())
 }
 
 pub fn reflect(v: Vec3, n: Vec3) -> Vec3 {
@@ -57,14 +62,40 @@ pub fn schlick(cosine: f32, refractive_index: f32) -> f32 {
 
 pub mod prelude {
     pub use super::aabb::AABB;
-    pub use super::bvh::BVH;
-    pub use super::camera::Camera;
+    pub use super::adaptive::AdaptiveOptions;
+    pub use super::aov::{Aov, FirstHit};
+    pub use super::bvh::{BuildOptions, FlatBVH, SplitMethod, BVH};
+    pub use super::camera::{
+        Camera, CameraConfig, EquirectangularCamera, FisheyeCamera, OrthographicCamera, Projection,
+        ThinLensCamera,
+    };
+    pub use super::denoise::{DenoiseOptions, Features};
+    pub use super::filter::{Filter, FilterKind};
     pub use super::hittable::{HitRecord, Hittable};
-    pub use super::material::{Dielectric, Lambertian, Material, Metal};
+    pub use super::material::{
+        Dielectric, DiffuseLight, Isotropic, Lambertian, Material, Metal, ScatterRecord,
+    };
+    pub use super::matrix::Mat4;
+    pub use super::medium::ConstantMedium;
+    pub use super::pdf::{
+        random_cosine_direction, random_unit_vector, CosinePdf, MixturePdf, Onb, Pdf,
+        UniformSpherePdf,
+    };
+    pub use super::quad::{Cuboid, Quad};
     pub use super::ray::Ray;
-    pub use super::rng::{thread_rng, Rng};
+    pub use super::rng::{
+        reseed_thread_rng, seeded_rng, set_seed, thread_rng, Rng, BVH_STREAM, CAMERA_STREAM,
+        SAMPLER_STREAM, SCENE_STREAM,
+    };
+    pub use super::sampler::{
+        HaltonSampler, IndependentSampler, Sampler, SobolSampler, StratifiedSampler,
+    };
     pub use super::sphere::{MovingSphere, Sphere};
-    pub use super::tracer::Tracer;
+    pub use super::texture::{Checker, Gradient, Texture};
+    pub use super::tonemap::{OutputTransform, ToneMap};
+    pub use super::tracer::{DepthLimits, Tracer};
+    pub use super::transform::Transform;
+    pub use super::triangle::{MeshFace, Triangle, TriangleMesh};
     pub use super::vec3;
     pub use super::vec3::Vec3;
     pub use super::{random_in_unit_disk, random_in_unit_sphere, reflect, refract, schlick};
@@ -1,58 +1,180 @@
-use crate::prelude::{thread_rng, Rng};
+use std::f32::consts::PI;
+use std::sync::Arc;
 
 use crate::prelude::{
-    random_in_unit_sphere, reflect, refract, schlick, vec3, HitRecord, Ray, Vec3,
+    random_in_unit_sphere, reflect, refract, schlick, vec3, CosinePdf, HitRecord, Pdf, Ray,
+    Sampler, Texture, UniformSpherePdf, Vec3,
 };
 
+/// How a path continues from a surface.
+pub enum ScatterRecord {
+    /// A single direction, such as a mirror reflection, followed with a weight
+    /// of `attenuation`. `transmitted` marks rays refracted through the surface.
+    Specular {
+        attenuation: Vec3,
+        ray: Ray,
+        transmitted: bool,
+    },
+    /// A direction to be drawn from `pdf`, weighted by the material's `eval`
+    /// over the density it was drawn with.
+    Diffuse { pdf: Box<dyn Pdf> },
+}
+
 pub trait Material: Sync + Send {
-    fn scatter(&self, r: &Ray, hit: &HitRecord) -> Option<(Vec3, Ray)>;
+    /// How a path arriving along `r` continues, drawing any random choices the
+    /// material makes from `sampler`.
+    fn scatter(&self, r: &Ray, hit: &HitRecord, sampler: &mut dyn Sampler)
+        -> Option<ScatterRecord>;
+
+    /// The BSDF for light arriving along `direction` and leaving towards the
+    /// origin of `r`, times the cosine at the surface. Zero for materials that
+    /// only scatter specularly.
+    fn eval(&self, _r: &Ray, _hit: &HitRecord, _direction: &Vec3) -> Vec3 {
+        Vec3::zeros()
+    }
+
+    /// The solid-angle density with which `scatter` picks `direction`.
+    fn pdf(&self, _r: &Ray, _hit: &HitRecord, _direction: &Vec3) -> f32 {
+        0.0
+    }
+
+    /// Radiance emitted by the surface towards the origin of `r`.
+    fn emitted(&self, _r: &Ray, _hit: &HitRecord) -> Vec3 {
+        Vec3::zeros()
+    }
+
+    /// The surface's reflectance, which guides the denoiser.
+    fn albedo(&self, _hit: &HitRecord) -> Vec3 {
+        Vec3::ones()
+    }
+
+    /// Tells materials apart in the material-id pass. Objects sharing a
+    /// material through an `Arc` share its id.
+    fn id(&self) -> usize {
+        self as *const Self as *const () as usize
+    }
+}
+
+impl<M: Material + ?Sized> Material for Arc<M> {
+    fn scatter(
+        &self,
+        r: &Ray,
+        hit: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        (**self).scatter(r, hit, sampler)
+    }
+
+    fn eval(&self, r: &Ray, hit: &HitRecord, direction: &Vec3) -> Vec3 {
+        (**self).eval(r, hit, direction)
+    }
+
+    fn pdf(&self, r: &Ray, hit: &HitRecord, direction: &Vec3) -> f32 {
+        (**self).pdf(r, hit, direction)
+    }
+
+    fn emitted(&self, r: &Ray, hit: &HitRecord) -> Vec3 {
+        (**self).emitted(r, hit)
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Vec3 {
+        (**self).albedo(hit)
+    }
+
+    fn id(&self) -> usize {
+        (**self).id()
+    }
 }
 
 pub struct Lambertian {
-    albedo: Vec3,
+    albedo: Box<dyn Texture>,
 }
 
 impl Lambertian {
-    pub fn new(albedo: Vec3) -> Box<Self> {
-        Box::new(Lambertian { albedo })
+    pub fn new<T: Texture + 'static>(albedo: T) -> Box<Self> {
+        Box::new(Lambertian {
+            albedo: Box::new(albedo),
+        })
+    }
+}
+
+// The surface normal on the side `r` arrives from, for one-sided geometry
+// such as triangles.
+fn facing_normal(r: &Ray, hit: &HitRecord) -> Vec3 {
+    if r.direction.dot(&hit.normal) > 0.0 {
+        -hit.normal
+    } else {
+        hit.normal
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, r: &Ray, hit: &HitRecord) -> Option<(Vec3, Ray)> {
-        let target = hit.p + hit.normal + random_in_unit_sphere();
-        let scattered = Ray::new(hit.p, target - hit.p, r.time);
-        Some((self.albedo, scattered))
+    fn scatter(
+        &self,
+        r: &Ray,
+        hit: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        Some(ScatterRecord::Diffuse {
+            pdf: Box::new(CosinePdf::new(facing_normal(r, hit))),
+        })
+    }
+
+    fn eval(&self, r: &Ray, hit: &HitRecord, direction: &Vec3) -> Vec3 {
+        self.albedo.value(hit.u, hit.v, &hit.p) * self.pdf(r, hit, direction)
+    }
+
+    fn pdf(&self, r: &Ray, hit: &HitRecord, direction: &Vec3) -> f32 {
+        let cosine = facing_normal(r, hit).dot(&direction.as_unit());
+        cosine.max(0.0) / PI
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Vec3 {
+        self.albedo.value(hit.u, hit.v, &hit.p)
     }
 }
 
 pub struct Metal {
-    albedo: Vec3,
+    albedo: Box<dyn Texture>,
     fuzz: f32,
 }
 
 impl Metal {
-    pub fn new(albedo: Vec3, fuzz: f32) -> Box<Self> {
+    pub fn new<T: Texture + 'static>(albedo: T, fuzz: f32) -> Box<Self> {
         Box::new(Metal {
-            albedo,
+            albedo: Box::new(albedo),
             fuzz: fuzz.min(1.0),
         })
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, r: &Ray, hit: &HitRecord) -> Option<(Vec3, Ray)> {
-        let reflected = reflect(r.direction.as_unit(), hit.normal);
+    fn scatter(
+        &self,
+        r: &Ray,
+        hit: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let normal = facing_normal(r, hit);
+        let reflected = reflect(r.direction.as_unit(), normal);
         let scattered = Ray::new(
             hit.p,
-            reflected + self.fuzz * random_in_unit_sphere(),
+            reflected + self.fuzz * random_in_unit_sphere(sampler),
             r.time,
         );
-        if scattered.direction.dot(&hit.normal) > 0.0 {
-            return Some((self.albedo, scattered));
+        if scattered.direction.dot(&normal) > 0.0 {
+            return Some(ScatterRecord::Specular {
+                attenuation: self.albedo.value(hit.u, hit.v, &hit.p),
+                ray: scattered,
+                transmitted: false,
+            });
         }
         None
     }
+
+    fn albedo(&self, hit: &HitRecord) -> Vec3 {
+        self.albedo.value(hit.u, hit.v, &hit.p)
+    }
 }
 
 pub struct Dielectric {
@@ -66,7 +188,12 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, r: &Ray, hit: &HitRecord) -> Option<(Vec3, Ray)> {
+    fn scatter(
+        &self,
+        r: &Ray,
+        hit: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let reflected = reflect(r.direction, hit.normal);
         let attenuation = vec3![1.0, 1.0, 1.0];
         let (outward_normal, ni_over_nt, cosine) = if r.direction.dot(&hit.normal) > 0.0 {
@@ -77,18 +204,88 @@ impl Material for Dielectric {
             (hit.normal, 1.0 / self.refractive_index, cosine)
         };
 
-        let scattered = if let Some(refracted) = refract(r.direction, outward_normal, ni_over_nt) {
-            let mut rng = thread_rng();
-            let reflect_prob = schlick(cosine, self.refractive_index);
-            if rng.gen::<f32>() < reflect_prob {
-                reflected
+        let (scattered, transmitted) =
+            if let Some(refracted) = refract(r.direction, outward_normal, ni_over_nt) {
+                let reflect_prob = schlick(cosine, self.refractive_index);
+                if sampler.get_1d() < reflect_prob {
+                    (reflected, false)
+                } else {
+                    (refracted, true)
+                }
             } else {
-                refracted
-            }
-        } else {
-            reflected
-        };
+                (reflected, false)
+            };
+
+        Some(ScatterRecord::Specular {
+            attenuation,
+            ray: Ray::new(hit.p, scattered, r.time),
+            transmitted,
+        })
+    }
+}
+
+/// An emitter that scatters no light.
+pub struct DiffuseLight {
+    emit: Box<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new<T: Texture + 'static>(emit: T) -> Box<Self> {
+        Box::new(DiffuseLight {
+            emit: Box::new(emit),
+        })
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _r: &Ray,
+        _hit: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        None
+    }
+
+    fn emitted(&self, _r: &Ray, hit: &HitRecord) -> Vec3 {
+        self.emit.value(hit.u, hit.v, &hit.p)
+    }
+}
+
+/// The phase function of a medium that scatters equally in every direction.
+pub struct Isotropic {
+    albedo: Box<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new<T: Texture + 'static>(albedo: T) -> Box<Self> {
+        Box::new(Isotropic {
+            albedo: Box::new(albedo),
+        })
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(
+        &self,
+        _r: &Ray,
+        _hit: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        Some(ScatterRecord::Diffuse {
+            pdf: Box::new(UniformSpherePdf),
+        })
+    }
+
+    fn eval(&self, r: &Ray, hit: &HitRecord, direction: &Vec3) -> Vec3 {
+        self.albedo.value(hit.u, hit.v, &hit.p) * self.pdf(r, hit, direction)
+    }
+
+    fn pdf(&self, _r: &Ray, _hit: &HitRecord, _direction: &Vec3) -> f32 {
+        1.0 / (4.0 * PI)
+    }
 
-        Some((attenuation, Ray::new(hit.p, scattered, r.time)))
+    fn albedo(&self, hit: &HitRecord) -> Vec3 {
+        self.albedo.value(hit.u, hit.v, &hit.p)
     }
 }
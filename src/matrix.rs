@@ -0,0 +1,142 @@
+use crate::prelude::{vec3, Vec3};
+
+/// A 4x4 row-major matrix acting on column vectors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn identity() -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Mat4 { m }
+    }
+
+    pub fn translate(offset: Vec3) -> Self {
+        let mut res = Mat4::identity();
+        res.m[0][3] = offset.x;
+        res.m[1][3] = offset.y;
+        res.m[2][3] = offset.z;
+        res
+    }
+
+    pub fn scale(factors: Vec3) -> Self {
+        let mut res = Mat4::identity();
+        res.m[0][0] = factors.x;
+        res.m[1][1] = factors.y;
+        res.m[2][2] = factors.z;
+        res
+    }
+
+    // a rotation by `degrees` in the plane of axes `a` and `b`, from a towards b
+    fn rotate(a: usize, b: usize, degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let mut res = Mat4::identity();
+        res.m[a][a] = cos;
+        res.m[a][b] = -sin;
+        res.m[b][a] = sin;
+        res.m[b][b] = cos;
+        res
+    }
+
+    /// A counter-clockwise rotation about the x axis.
+    pub fn rotate_x(degrees: f32) -> Self {
+        Mat4::rotate(1, 2, degrees)
+    }
+
+    /// A counter-clockwise rotation about the y axis.
+    pub fn rotate_y(degrees: f32) -> Self {
+        Mat4::rotate(2, 0, degrees)
+    }
+
+    /// A counter-clockwise rotation about the z axis.
+    pub fn rotate_z(degrees: f32) -> Self {
+        Mat4::rotate(0, 1, degrees)
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, x) in row.iter_mut().enumerate() {
+                *x = self.m[j][i];
+            }
+        }
+        Mat4 { m }
+    }
+
+    /// The inverse by Gauss-Jordan elimination, or `None` if singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = [[0f64; 8]; 4];
+        for (i, row) in a.iter_mut().enumerate() {
+            for (x, &y) in row.iter_mut().zip(self.m[i].iter()) {
+                *x = y as f64;
+            }
+            row[4 + i] = 1.0;
+        }
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().partial_cmp(&a[j][col].abs()).unwrap())
+                .unwrap();
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            let p = a[col][col];
+            a[col].iter_mut().for_each(|x| *x /= p);
+            let pivot_row = a[col];
+            for (i, row) in a.iter_mut().enumerate() {
+                if i != col {
+                    let f = row[col];
+                    for (x, y) in row.iter_mut().zip(pivot_row.iter()) {
+                        *x -= f * y;
+                    }
+                }
+            }
+        }
+        let mut m = [[0.0; 4]; 4];
+        for (row, inv) in m.iter_mut().zip(a.iter()) {
+            for (x, y) in row.iter_mut().zip(inv[4..].iter()) {
+                *x = *y as f32;
+            }
+        }
+        Some(Mat4 { m })
+    }
+
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3];
+        let y = m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3];
+        let z = m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3];
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        if w == 1.0 {
+            vec3![x, y, z]
+        } else {
+            vec3![x, y, z] / w
+        }
+    }
+
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        vec3![
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z
+        ]
+    }
+}
+
+impl std::ops::Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, x) in row.iter_mut().enumerate() {
+                *x = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Mat4 { m }
+    }
+}
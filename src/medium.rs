@@ -0,0 +1,61 @@
+use crate::prelude::{
+    thread_rng, vec3, HitRecord, Hittable, Isotropic, Material, Ray, Rng, Texture, Vec3, AABB,
+};
+
+/// A volume of uniform density filling a closed, convex `boundary`, such as
+/// smoke or fog. The boundary's own material is never used.
+pub struct ConstantMedium {
+    boundary: Box<dyn Hittable + Sync>,
+    density: f32,
+    phase: Box<dyn Material>,
+}
+
+impl ConstantMedium {
+    pub fn new<T: Texture + 'static>(
+        boundary: Box<dyn Hittable + Sync>,
+        density: f32,
+        albedo: T,
+    ) -> Self {
+        ConstantMedium {
+            boundary,
+            density,
+            phase: Isotropic::new(albedo),
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, tmin: f32, tmax: f32) -> Option<HitRecord<'_>> {
+        // the whole interval the ray spends inside the boundary, clipped
+        let entry = self.boundary.hit(r, f32::MIN, f32::MAX)?.t;
+        let exit = self.boundary.hit(r, entry + 1e-4, f32::MAX)?.t;
+        let t0 = entry.max(tmin);
+        let t1 = exit.min(tmax);
+        if t0 >= t1 {
+            return None;
+        }
+
+        // sample a free-flight distance; rays that outrun it pass through
+        let len = r.direction.len();
+        let distance = -(1.0 - thread_rng().gen::<f32>()).ln() / self.density;
+        if distance > (t1 - t0) * len {
+            return None;
+        }
+        let t = t0 + distance / len;
+        // a scattering event has no surface, so the normal is arbitrary
+        Some(HitRecord::new(
+            t,
+            r.point_at(t),
+            vec3![1, 0, 0],
+            self.phase.as_ref(),
+        ))
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.boundary.bounding_box(t0, t1)
+    }
+
+    fn into_box(self) -> Box<dyn Hittable + Sync> {
+        Box::new(self) as Box<dyn Hittable + Sync>
+    }
+}
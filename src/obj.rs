@@ -0,0 +1,342 @@
+//! Wavefront OBJ and MTL loading.
+//!
+//! Faces are fan-triangulated, and MTL materials are mapped onto the closest
+//! built-in `Material`: emissive materials become `DiffuseLight`, transparent
+//! ones `Dielectric`, mirror-like ones `Metal`, and everything else `Lambertian`.
+
+use std::fs;
+use std::path::Path;
+
+use failure::{format_err, Error};
+
+use crate::prelude::{vec3, Dielectric, DiffuseLight, Lambertian, Material, Metal, Vec3};
+use crate::triangle::{MeshFace, TriangleMesh};
+
+#[derive(Clone, Copy, Debug)]
+struct MtlSpec {
+    diffuse: Vec3,
+    specular: Vec3,
+    emission: Vec3,
+    shininess: f32,
+    refractive_index: f32,
+    dissolve: f32,
+    illum: u32,
+}
+
+impl Default for MtlSpec {
+    fn default() -> Self {
+        MtlSpec {
+            diffuse: vec3![0.8, 0.8, 0.8],
+            specular: Vec3::zeros(),
+            emission: Vec3::zeros(),
+            shininess: 0.0,
+            refractive_index: 1.0,
+            dissolve: 1.0,
+            illum: 1,
+        }
+    }
+}
+
+impl MtlSpec {
+    fn build(&self) -> Box<dyn Material> {
+        let transparent = self.dissolve < 1.0 || [4, 6, 7, 9].contains(&self.illum);
+        let mirror = self.illum == 3 || (self.illum == 2 && self.specular.len() > 0.0);
+        if self.emission.len() > 0.0 {
+            DiffuseLight::new(self.emission)
+        } else if transparent {
+            let refractive_index = if self.refractive_index > 1.0 {
+                self.refractive_index
+            } else {
+                1.5
+            };
+            Dielectric::new(refractive_index)
+        } else if mirror && self.specular.len() > self.diffuse.len() {
+            // map the Phong exponent onto a roughness
+            let fuzz = (2.0 / (self.shininess + 2.0)).sqrt();
+            Metal::new(self.specular, fuzz)
+        } else {
+            Lambertian::new(self.diffuse)
+        }
+    }
+}
+
+fn error(path: &Path, line: usize, msg: String) -> Error {
+    format_err!("{}: line {}: {}", path.display(), line, msg)
+}
+
+fn floats(path: &Path, line: usize, args: &[&str], n: usize) -> Result<Vec<f32>, Error> {
+    if args.len() < n {
+        return Err(error(path, line, format!("expected {} numbers", n)));
+    }
+    args[0..n]
+        .iter()
+        .map(|a| {
+            a.parse::<f32>()
+                .map_err(|_| error(path, line, format!("expected a number but got '{}'", a)))
+        })
+        .collect()
+}
+
+fn read(path: &Path) -> Result<String, Error> {
+    fs::read_to_string(path).map_err(|e| format_err!("failed to read {}: {}", path.display(), e))
+}
+
+fn load_mtl(path: &Path, specs: &mut Vec<(String, MtlSpec)>) -> Result<(), Error> {
+    for (i, text) in read(path)?.lines().enumerate() {
+        let line = i + 1;
+        let mut tokens = text.split('#').next().unwrap_or("").split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args = tokens.collect::<Vec<_>>();
+        if keyword == "newmtl" {
+            let name = args.join(" ");
+            specs.push((name, MtlSpec::default()));
+            continue;
+        }
+        let spec = match specs.last_mut() {
+            Some((_, spec)) => spec,
+            None => return Err(error(path, line, format!("'{}' before newmtl", keyword))),
+        };
+        match keyword {
+            "Kd" => {
+                let v = floats(path, line, &args, 3)?;
+                spec.diffuse = vec3![v[0], v[1], v[2]];
+            }
+            "Ks" => {
+                let v = floats(path, line, &args, 3)?;
+                spec.specular = vec3![v[0], v[1], v[2]];
+            }
+            "Ke" => {
+                let v = floats(path, line, &args, 3)?;
+                spec.emission = vec3![v[0], v[1], v[2]];
+            }
+            "Ns" => spec.shininess = floats(path, line, &args, 1)?[0],
+            "Ni" => spec.refractive_index = floats(path, line, &args, 1)?[0],
+            "d" => spec.dissolve = floats(path, line, &args, 1)?[0],
+            "Tr" => spec.dissolve = 1.0 - floats(path, line, &args, 1)?[0],
+            "illum" => spec.illum = floats(path, line, &args, 1)?[0] as u32,
+            // texture maps, ambient color and the like have no counterpart
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Resolve a 1-based, possibly negative (relative) OBJ index.
+fn index(path: &Path, line: usize, token: &str, len: usize) -> Result<usize, Error> {
+    let i = token
+        .parse::<isize>()
+        .map_err(|_| error(path, line, format!("invalid index '{}'", token)))?;
+    let resolved = if i < 0 { len as isize + i } else { i - 1 };
+    if resolved < 0 || resolved as usize >= len {
+        return Err(error(path, line, format!("index {} out of range", i)));
+    }
+    Ok(resolved as usize)
+}
+
+fn all3(xs: [Option<usize>; 3]) -> Option<[usize; 3]> {
+    match xs {
+        [Some(a), Some(b), Some(c)] => Some([a, b, c]),
+        _ => None,
+    }
+}
+
+/// Load a mesh from an OBJ file, along with any MTL libraries it references.
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<TriangleMesh, Error> {
+    let path = path.as_ref();
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut uvs = vec![];
+    let mut faces = vec![];
+    let mut specs = vec![];
+    let mut material = None;
+
+    for (i, text) in read(path)?.lines().enumerate() {
+        let line = i + 1;
+        let mut tokens = text.split('#').next().unwrap_or("").split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args = tokens.collect::<Vec<_>>();
+        match keyword {
+            "v" => {
+                let v = floats(path, line, &args, 3)?;
+                positions.push(vec3![v[0], v[1], v[2]]);
+            }
+            "vn" => {
+                let v = floats(path, line, &args, 3)?;
+                normals.push(vec3![v[0], v[1], v[2]]);
+            }
+            "vt" => {
+                let v = floats(path, line, &args, 1)?;
+                let t = args.get(1).and_then(|t| t.parse::<f32>().ok());
+                uvs.push((v[0], t.unwrap_or(0.0)));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(error(path, line, "face has fewer than 3 vertices".into()));
+                }
+                let mut corners = vec![];
+                for arg in args.iter() {
+                    let mut parts = arg.split('/');
+                    let p = index(path, line, parts.next().unwrap(), positions.len())?;
+                    let t = match parts.next() {
+                        Some("") | None => None,
+                        Some(t) => Some(index(path, line, t, uvs.len())?),
+                    };
+                    let n = match parts.next() {
+                        Some("") | None => None,
+                        Some(n) => Some(index(path, line, n, normals.len())?),
+                    };
+                    corners.push((p, t, n));
+                }
+                let material = match material {
+                    Some(material) => material,
+                    None => {
+                        // faces without usemtl get the default material
+                        specs.push((String::new(), MtlSpec::default()));
+                        material = Some(specs.len() - 1);
+                        specs.len() - 1
+                    }
+                };
+                for k in 1..corners.len() - 1 {
+                    let tri = [corners[0], corners[k], corners[k + 1]];
+                    faces.push(MeshFace {
+                        positions: [tri[0].0, tri[1].0, tri[2].0],
+                        uvs: all3([tri[0].1, tri[1].1, tri[2].1]),
+                        normals: all3([tri[0].2, tri[1].2, tri[2].2]),
+                        material,
+                    });
+                }
+            }
+            "mtllib" => {
+                for lib in args.iter() {
+                    load_mtl(&dir.join(lib), &mut specs)?;
+                }
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                match specs.iter().rposition(|(n, _)| *n == name) {
+                    Some(i) => material = Some(i),
+                    None => {
+                        return Err(error(path, line, format!("undefined material '{}'", name)))
+                    }
+                }
+            }
+            // groups, smoothing groups, lines and points don't affect the triangles
+            _ => {}
+        }
+    }
+
+    if faces.is_empty() {
+        return Err(format_err!("{}: no faces", path.display()));
+    }
+    let materials = specs.iter().map(|(_, spec)| spec.build()).collect();
+    Ok(TriangleMesh {
+        positions,
+        normals,
+        uvs,
+        faces,
+        materials,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // Write `files` into a fresh directory and return the path of the first.
+    fn write(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("riaw-obj-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, contents) in files {
+            fs::write(dir.join(name), contents).unwrap();
+        }
+        dir.join(files[0].0)
+    }
+
+    fn error(path: &Path) -> String {
+        match load_obj(path) {
+            Ok(_) => panic!("expected an error loading {}", path.display()),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn faces_are_fan_triangulated() {
+        let path = write(
+            "fan",
+            &[(
+                "quad.obj",
+                "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\n\
+                 f 1/1/1 2/1/1 3/1/1 4/1/1\nf 1//1 3//1 4//1\nf 1 2 3\n",
+            )],
+        );
+        let mesh = load_obj(&path).unwrap();
+        let positions = mesh.faces.iter().map(|f| f.positions).collect::<Vec<_>>();
+        assert_eq!(positions, vec![[0, 1, 2], [0, 2, 3], [0, 2, 3], [0, 1, 2]]);
+        assert_eq!(mesh.faces[0].uvs, Some([0, 0, 0]));
+        assert_eq!(mesh.faces[0].normals, Some([0, 0, 0]));
+        assert_eq!(mesh.faces[2].uvs, None);
+        assert_eq!(mesh.faces[2].normals, Some([0, 0, 0]));
+        assert_eq!(mesh.faces[3].normals, None);
+    }
+
+    #[test]
+    fn negative_indices_are_relative() {
+        let path = write(
+            "relative",
+            &[(
+                "tris.obj",
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\nv 0 0 1\nf -1 -3 -4\n",
+            )],
+        );
+        let mesh = load_obj(&path).unwrap();
+        assert_eq!(mesh.faces[0].positions, [0, 1, 2]);
+        assert_eq!(mesh.faces[1].positions, [3, 1, 0]);
+
+        let path = write(
+            "out-of-range",
+            &[("bad.obj", "v 0 0 0\nv 1 0 0\nf 1 2 -3\n")],
+        );
+        assert!(error(&path).ends_with("line 3: index -3 out of range"));
+    }
+
+    #[test]
+    fn materials_are_looked_up_by_name() {
+        let path = write(
+            "mtl",
+            &[
+                (
+                    "scene.obj",
+                    "mtllib colors.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\n\
+                     f 1 2 3\nusemtl blue\nf 1 2 3\nusemtl red\nf 1 2 3\n",
+                ),
+                (
+                    "colors.mtl",
+                    "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n",
+                ),
+            ],
+        );
+        let mesh = load_obj(&path).unwrap();
+        // faces before any usemtl get a default material after the library's
+        let materials = mesh.faces.iter().map(|f| f.material).collect::<Vec<_>>();
+        assert_eq!(materials, vec![2, 1, 0]);
+        assert_eq!(mesh.materials.len(), 3);
+
+        let path = write(
+            "undefined-mtl",
+            &[(
+                "bad.obj",
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl green\nf 1 2 3\n",
+            )],
+        );
+        assert!(error(&path).ends_with("line 4: undefined material 'green'"));
+    }
+}
@@ -0,0 +1,318 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use failure::{bail, Error};
+
+use crate::prelude::Vec3;
+
+fn check_len<T>(buffer: &[T], width: usize, height: usize) {
+    if buffer.len() < width * height {
+        panic!("image buffer is insufficiently sized");
+    }
+}
+
+fn rgb_bytes(buffer: &[u32], width: usize, height: usize) -> Vec<u8> {
+    check_len(buffer, width, height);
+    let mut data = Vec::with_capacity(width * height * 3);
+    for &argb in &buffer[0..width * height] {
+        data.push(((argb >> 16) & 0xff) as u8);
+        data.push(((argb >> 8) & 0xff) as u8);
+        data.push((argb & 0xff) as u8);
+    }
+    data
+}
+
+/// Write an ARGB buffer, stored top row first, as a binary (P6) PPM.
+pub fn write_ppm<W: Write>(
+    mut w: W,
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+) -> Result<(), Error> {
+    write!(w, "P6\n{} {}\n255\n", width, height)?;
+    w.write_all(&rgb_bytes(buffer, width, height))?;
+    w.flush()?;
+    Ok(())
+}
+
+/// Write an ARGB buffer, stored top row first, as an 8-bit RGB PNG.
+pub fn write_png<W: Write>(w: W, buffer: &[u32], width: usize, height: usize) -> Result<(), Error> {
+    let mut encoder = png::Encoder::new(w, width as u32, height as u32);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&rgb_bytes(buffer, width, height))?;
+    Ok(())
+}
+
+fn rgbe(v: Vec3) -> [u8; 4] {
+    let m = v.x.max(v.y).max(v.z);
+    if m < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    // frexp: m = f * 2^e with f in [0.5, 1)
+    let mut e = m.log2().floor() as i32 + 1;
+    if m / 2f32.powi(e) >= 1.0 {
+        e += 1;
+    }
+    let scale = 256.0 / 2f32.powi(e);
+    [
+        (v.x.max(0.0) * scale) as u8,
+        (v.y.max(0.0) * scale) as u8,
+        (v.z.max(0.0) * scale) as u8,
+        (e + 128) as u8,
+    ]
+}
+
+/// Write linear radiance, stored top row first, as an uncompressed Radiance HDR (RGBE) image.
+pub fn write_hdr<W: Write>(
+    mut w: W,
+    pixels: &[Vec3],
+    width: usize,
+    height: usize,
+) -> Result<(), Error> {
+    check_len(pixels, width, height);
+    write!(
+        w,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        height, width
+    )?;
+    for &v in &pixels[0..width * height] {
+        w.write_all(&rgbe(v))?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Write linear radiance, stored top row first, as a little-endian color PFM.
+pub fn write_pfm<W: Write>(
+    mut w: W,
+    pixels: &[Vec3],
+    width: usize,
+    height: usize,
+) -> Result<(), Error> {
+    check_len(pixels, width, height);
+    write!(w, "PF\n{} {}\n-1.0\n", width, height)?;
+    // PFM scanlines run bottom to top
+    for row in pixels[0..width * height].chunks(width).rev() {
+        for v in row {
+            for c in v.to_array().iter() {
+                w.write_all(&c.to_le_bytes())?;
+            }
+        }
+    }
+    w.flush()?;
+    Ok(())
+}
+
+fn exr_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Write linear radiance, stored top row first, as an uncompressed scanline OpenEXR with
+/// 32-bit float R, G and B channels.
+pub fn write_exr<W: Write>(
+    mut w: W,
+    pixels: &[Vec3],
+    width: usize,
+    height: usize,
+) -> Result<(), Error> {
+    check_len(pixels, width, height);
+    // channels are stored in alphabetical order
+    const CHANNELS: [(&str, usize); 3] = [("B", 2), ("G", 1), ("R", 0)];
+    const FLOAT: i32 = 2;
+
+    let mut header = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+    let mut chlist = vec![];
+    for (name, _) in CHANNELS.iter() {
+        chlist.extend_from_slice(name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&FLOAT.to_le_bytes());
+        // pLinear and reserved bytes
+        chlist.extend_from_slice(&[0, 0, 0, 0]);
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+    }
+    chlist.push(0);
+    exr_attribute(&mut header, "channels", "chlist", &chlist);
+    exr_attribute(&mut header, "compression", "compression", &[0]);
+    let mut window = vec![];
+    for c in [0, 0, width as i32 - 1, height as i32 - 1].iter() {
+        window.extend_from_slice(&c.to_le_bytes());
+    }
+    exr_attribute(&mut header, "dataWindow", "box2i", &window);
+    exr_attribute(&mut header, "displayWindow", "box2i", &window);
+    exr_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    exr_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    exr_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    exr_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    header.push(0);
+    w.write_all(&header)?;
+
+    // one scanline per chunk: y, byte count, then each channel's samples
+    let line_size = CHANNELS.len() * width * 4;
+    let chunk_size = 8 + line_size;
+    let table_end = header.len() + 8 * height;
+    for y in 0..height {
+        w.write_all(&((table_end + y * chunk_size) as u64).to_le_bytes())?;
+    }
+    for (y, row) in pixels[0..width * height].chunks(width).enumerate() {
+        w.write_all(&(y as i32).to_le_bytes())?;
+        w.write_all(&(line_size as i32).to_le_bytes())?;
+        for &(_, c) in CHANNELS.iter() {
+            for v in row {
+                w.write_all(&v[c].to_le_bytes())?;
+            }
+        }
+    }
+    w.flush()?;
+    Ok(())
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+}
+
+/// Whether `path` names a high dynamic range format understood by `save_hdr_image`.
+pub fn is_hdr_path<P: AsRef<Path>>(path: P) -> bool {
+    matches!(
+        extension(path.as_ref()).as_deref(),
+        Some("hdr") | Some("pfm") | Some("exr")
+    )
+}
+
+/// Save linear radiance to `path`, choosing the format from its extension.
+pub fn save_hdr_image<P: AsRef<Path>>(
+    path: P,
+    pixels: &[Vec3],
+    width: usize,
+    height: usize,
+) -> Result<(), Error> {
+    let path = path.as_ref();
+    match extension(path).as_deref() {
+        Some("hdr") => write_hdr(BufWriter::new(File::create(path)?), pixels, width, height),
+        Some("pfm") => write_pfm(BufWriter::new(File::create(path)?), pixels, width, height),
+        Some("exr") => write_exr(BufWriter::new(File::create(path)?), pixels, width, height),
+        _ => bail!("unsupported HDR image format: {}", path.display()),
+    }
+}
+
+/// Save an ARGB buffer to `path`, choosing the format from its extension.
+pub fn save_image<P: AsRef<Path>>(
+    path: P,
+    buffer: &[u32],
+    width: usize,
+    height: usize,
+) -> Result<(), Error> {
+    let path = path.as_ref();
+    match extension(path).as_deref() {
+        Some("ppm") => write_ppm(BufWriter::new(File::create(path)?), buffer, width, height),
+        Some("png") => write_png(BufWriter::new(File::create(path)?), buffer, width, height),
+        _ => bail!("unsupported image format: {}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::vec3;
+
+    fn f32s(bytes: &[u8]) -> Vec<f32> {
+        bytes
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    }
+
+    #[test]
+    fn hdr_layout() {
+        let pixels = [vec3![1.0, 0.5, 0.25], Vec3::zeros()];
+        let mut out = vec![];
+        write_hdr(&mut out, &pixels, 2, 1).unwrap();
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n";
+        assert_eq!(&out[..header.len()], &header[..]);
+        assert_eq!(&out[header.len()..], &[128, 64, 32, 129, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn pfm_layout() {
+        let pixels = [vec3![1.0, 2.0, 3.0], vec3![4.0, 5.0, 6.0]];
+        let mut out = vec![];
+        write_pfm(&mut out, &pixels, 1, 2).unwrap();
+        let header = b"PF\n1 2\n-1.0\n";
+        assert_eq!(&out[..header.len()], &header[..]);
+        // bottom row first
+        assert_eq!(
+            f32s(&out[header.len()..]),
+            vec![4.0, 5.0, 6.0, 1.0, 2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn exr_layout() {
+        let (width, height) = (2, 2);
+        let pixels = [
+            vec3![1.0, 2.0, 3.0],
+            vec3![4.0, 5.0, 6.0],
+            vec3![7.0, 8.0, 9.0],
+            vec3![10.0, 11.0, 12.0],
+        ];
+        let mut out = vec![];
+        write_exr(&mut out, &pixels, width, height).unwrap();
+
+        // magic number, then version 2 with no flags
+        assert_eq!(&out[..8], &[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+        let contains = |needle: &[u8]| out.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(b"channels\0chlist\0"));
+        assert!(contains(b"compression\0compression\0\x01\0\0\0\0"));
+        assert!(contains(b"dataWindow\0box2i\0"));
+
+        let line_size = 3 * width * 4;
+        let chunk_size = 8 + line_size;
+        // the offset table follows the header, which ends in a null byte
+        let table = out.len() - height * chunk_size - 8 * height;
+        assert_eq!(out[table - 1], 0);
+        let table = &out[table..];
+        let offset = |y: usize| {
+            let mut b = [0; 8];
+            b.copy_from_slice(&table[8 * y..8 * y + 8]);
+            u64::from_le_bytes(b) as usize
+        };
+        for y in 0..height {
+            let chunk = &out[offset(y)..offset(y) + chunk_size];
+            assert_eq!(
+                i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+                y as i32
+            );
+            assert_eq!(
+                i32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]),
+                line_size as i32
+            );
+            // channels in alphabetical order, each a whole scanline
+            let row = &pixels[y * width..(y + 1) * width];
+            let expected = [2, 1, 0]
+                .iter()
+                .flat_map(|&c| row.iter().map(move |v| v[c]))
+                .collect::<Vec<_>>();
+            assert_eq!(f32s(&chunk[8..]), expected);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::f32::consts::PI;
+
+use crate::prelude::{vec3, Sampler, Vec3};
+use crate::sampler::{cosine_hemisphere, uniform_sphere};
+
+/// An orthonormal basis whose `w` axis is a given direction.
+#[derive(Clone, Copy, Debug)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn from_w(n: Vec3) -> Self {
+        let w = n.as_unit();
+        // any axis not parallel to w will do
+        let a = if w.x.abs() > 0.9 {
+            vec3![0, 1, 0]
+        } else {
+            vec3![1, 0, 0]
+        };
+        let v = w.cross(&a).as_unit();
+        let u = w.cross(&v);
+        Onb { u, v, w }
+    }
+
+    /// Map coordinates in this basis to world space.
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        a.x * self.u + a.y * self.v + a.z * self.w
+    }
+}
+
+/// A unit direction about +z, with density `cos(theta) / pi`.
+pub fn random_cosine_direction(sampler: &mut dyn Sampler) -> Vec3 {
+    cosine_hemisphere(sampler.get_2d())
+}
+
+/// A uniformly distributed unit direction.
+pub fn random_unit_vector(sampler: &mut dyn Sampler) -> Vec3 {
+    uniform_sphere(sampler.get_2d())
+}
+
+/// A probability density over directions, with respect to solid angle.
+pub trait Pdf {
+    /// The density of generating `direction`, which need not be unit length.
+    fn value(&self, direction: &Vec3) -> f32;
+    /// Draw a unit direction from the distribution.
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3;
+}
+
+/// Directions in the hemisphere about a normal, weighted by their cosine.
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(normal: Vec3) -> Self {
+        CosinePdf {
+            uvw: Onb::from_w(normal),
+        }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: &Vec3) -> f32 {
+        let cosine = direction.as_unit().dot(&self.uvw.w);
+        cosine.max(0.0) / PI
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.uvw.local(random_cosine_direction(sampler))
+    }
+}
+
+/// Every direction equally likely.
+pub struct UniformSpherePdf;
+
+impl Pdf for UniformSpherePdf {
+    fn value(&self, _direction: &Vec3) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        random_unit_vector(sampler)
+    }
+}
+
+/// Picks from `a` with probability `weight`, and from `b` otherwise.
+pub struct MixturePdf<'a> {
+    a: &'a dyn Pdf,
+    b: &'a dyn Pdf,
+    weight: f32,
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(a: &'a dyn Pdf, b: &'a dyn Pdf, weight: f32) -> Self {
+        MixturePdf { a, b, weight }
+    }
+}
+
+impl<'a> Pdf for MixturePdf<'a> {
+    fn value(&self, direction: &Vec3) -> f32 {
+        self.weight * self.a.value(direction) + (1.0 - self.weight) * self.b.value(direction)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        if sampler.get_1d() < self.weight {
+            self.a.generate(sampler)
+        } else {
+            self.b.generate(sampler)
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::prelude::{vec3, HitRecord, Hittable, Material, Ray, Sampler, Vec3, AABB};
+
+// Planar objects get this much thickness in their bounding boxes.
+const PAD: f32 = 1e-4;
+
+/// The parallelogram spanned by `u` and `v` from the corner `q`.
+#[derive(Clone, Copy, Debug)]
+struct Parallelogram {
+    q: Vec3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    d: f32,
+    w: Vec3,
+    area: f32,
+}
+
+impl Parallelogram {
+    fn new(q: Vec3, u: Vec3, v: Vec3) -> Self {
+        let n = u.cross(&v);
+        let normal = n.as_unit();
+        Parallelogram {
+            q,
+            u,
+            v,
+            normal,
+            d: normal.dot(&q),
+            w: n / n.squared_len(),
+            area: n.len(),
+        }
+    }
+
+    // the ray parameter, hit point and planar coordinates of an intersection
+    fn intersect(&self, r: &Ray, tmin: f32, tmax: f32) -> Option<(f32, Vec3, f32, f32)> {
+        let denom = self.normal.dot(&r.direction);
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+        let t = (self.d - self.normal.dot(&r.origin)) / denom;
+        if t <= tmin || t >= tmax {
+            return None;
+        }
+        let p = r.point_at(t);
+        let planar = p - self.q;
+        let alpha = self.w.dot(&planar.cross(&self.v));
+        let beta = self.w.dot(&self.u.cross(&planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+        Some((t, p, alpha, beta))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let corners = [self.q + self.u, self.q + self.v, self.q + self.u + self.v];
+        corners
+            .iter()
+            .fold(AABB::new(self.q, self.q), |bbox, &c| {
+                AABB::surrounding_box(bbox, AABB::new(c, c))
+            })
+            .padded(PAD)
+    }
+}
+
+/// A parallelogram with its normal along `u × v`.
+pub struct Quad {
+    shape: Parallelogram,
+    material: Box<dyn Material>,
+}
+
+impl Quad {
+    pub fn new(q: Vec3, u: Vec3, v: Vec3, material: Box<dyn Material>) -> Self {
+        Quad {
+            shape: Parallelogram::new(q, u, v),
+            material,
+        }
+    }
+
+    /// The rectangle `[x0, x1] × [y0, y1]` in the plane `z = k`, facing +z.
+    pub fn xy(x0: f32, x1: f32, y0: f32, y1: f32, k: f32, material: Box<dyn Material>) -> Self {
+        Quad::new(
+            vec3![x0, y0, k],
+            vec3![x1 - x0, 0, 0],
+            vec3![0, y1 - y0, 0],
+            material,
+        )
+    }
+
+    /// The rectangle `[x0, x1] × [z0, z1]` in the plane `y = k`, facing +y.
+    pub fn xz(x0: f32, x1: f32, z0: f32, z1: f32, k: f32, material: Box<dyn Material>) -> Self {
+        Quad::new(
+            vec3![x0, k, z0],
+            vec3![0, 0, z1 - z0],
+            vec3![x1 - x0, 0, 0],
+            material,
+        )
+    }
+
+    /// The rectangle `[y0, y1] × [z0, z1]` in the plane `x = k`, facing +x.
+    pub fn yz(y0: f32, y1: f32, z0: f32, z1: f32, k: f32, material: Box<dyn Material>) -> Self {
+        Quad::new(
+            vec3![k, y0, z0],
+            vec3![0, y1 - y0, 0],
+            vec3![0, 0, z1 - z0],
+            material,
+        )
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, tmin: f32, tmax: f32) -> Option<HitRecord<'_>> {
+        let (t, p, alpha, beta) = self.shape.intersect(r, tmin, tmax)?;
+        Some(HitRecord::new(t, p, self.shape.normal, self.material.as_ref()).with_uv(alpha, beta))
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        Some(self.shape.bounding_box())
+    }
+
+    fn into_box(self) -> Box<dyn Hittable + Sync> {
+        Box::new(self) as Box<dyn Hittable + Sync>
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        let r = Ray::new(origin, direction, 0.0);
+        match self.shape.intersect(&r, 1e-3, f32::MAX) {
+            Some((t, _, _, _)) => {
+                // convert the uniform density over the area to solid angle
+                let distance_squared = t * t * direction.squared_len();
+                let cosine = (direction.dot(&self.shape.normal) / direction.len()).abs();
+                distance_squared / (cosine * self.shape.area)
+            }
+            None => 0.0,
+        }
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (a, b) = sampler.get_2d();
+        let p = self.shape.q + a * self.shape.u + b * self.shape.v;
+        p - origin
+    }
+}
+
+/// An axis-aligned box of six outward-facing quads sharing one material.
+pub struct Cuboid {
+    faces: [Parallelogram; 6],
+    bbox: AABB,
+    material: Box<dyn Material>,
+}
+
+impl Cuboid {
+    pub fn new(a: Vec3, b: Vec3, material: Box<dyn Material>) -> Self {
+        let min = vec3![a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)];
+        let max = vec3![a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)];
+        let dx = vec3![max.x - min.x, 0, 0];
+        let dy = vec3![0, max.y - min.y, 0];
+        let dz = vec3![0, 0, max.z - min.z];
+        let faces = [
+            Parallelogram::new(vec3![min.x, min.y, max.z], dx, dy),
+            Parallelogram::new(vec3![max.x, min.y, max.z], -dz, dy),
+            Parallelogram::new(vec3![max.x, min.y, min.z], -dx, dy),
+            Parallelogram::new(vec3![min.x, min.y, min.z], dz, dy),
+            Parallelogram::new(vec3![min.x, max.y, max.z], dx, -dz),
+            Parallelogram::new(vec3![min.x, min.y, min.z], dx, dz),
+        ];
+        Cuboid {
+            faces,
+            bbox: AABB::new(min, max).padded(PAD),
+            material,
+        }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, r: &Ray, tmin: f32, mut tmax: f32) -> Option<HitRecord<'_>> {
+        let mut result = None;
+        for face in self.faces.iter() {
+            if let Some((t, p, alpha, beta)) = face.intersect(r, tmin, tmax) {
+                tmax = t;
+                result = Some(
+                    HitRecord::new(t, p, face.normal, self.material.as_ref()).with_uv(alpha, beta),
+                );
+            }
+        }
+        result
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        Some(self.bbox)
+    }
+
+    fn into_box(self) -> Box<dyn Hittable + Sync> {
+        Box::new(self) as Box<dyn Hittable + Sync>
+    }
+}
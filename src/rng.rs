@@ -1,15 +1,55 @@
 use std::cell::UnsafeCell;
 use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-pub use rand::{rngs::OsRng, Rng, RngCore, SeedableRng};
+pub use rand::{Rng, RngCore};
 
 type FastRng = rand_pcg::Pcg64Mcg;
 
+// Streams with the high bit set are reserved for scene-level randomness; pixels
+// use their linear index as their stream, and samplers their linear index
+// offset by `SAMPLER_STREAM`, so their draws are independent of the path's.
+pub const SCENE_STREAM: u64 = 1 << 63;
+pub const BVH_STREAM: u64 = SCENE_STREAM + 1;
+pub const CAMERA_STREAM: u64 = SCENE_STREAM + 2;
+pub const SAMPLER_STREAM: u64 = 1 << 62;
+
+static SEED: AtomicU64 = AtomicU64::new(0);
+
+/// Set the global seed from which every random stream is derived.
+pub fn set_seed(seed: u64) {
+    SEED.store(seed, Ordering::Relaxed);
+}
+
+pub fn seed() -> u64 {
+    SEED.load(Ordering::Relaxed)
+}
+
+pub(crate) fn splitmix64(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A generator determined only by the global seed, `stream` and `counter`.
+pub fn seeded_rng(stream: u64, counter: u64) -> FastRng {
+    let hi = splitmix64(seed() ^ splitmix64(stream));
+    let lo = splitmix64(hi ^ splitmix64(counter.wrapping_add(stream)));
+    FastRng::new(((hi as u128) << 64) | lo as u128)
+}
+
+/// Replace this thread's generator with `seeded_rng(stream, counter)`, so that
+/// every `thread_rng` draw until the next reseed is reproducible regardless of
+/// which thread does the work.
+pub fn reseed_thread_rng(stream: u64, counter: u64) {
+    THREAD_RNG_KEY.with(|t| unsafe { *t.get() = seeded_rng(stream, counter) });
+}
+
 thread_local!(
     pub static THREAD_RNG_KEY: UnsafeCell<FastRng> = {
-        let mut seed = [0u8; 16];
-        OsRng.fill_bytes(&mut seed[..]);
-        UnsafeCell::new(FastRng::from_seed(seed))
+        let rng = seeded_rng(SCENE_STREAM, u64::MAX);
+        UnsafeCell::new(rng)
     };
 );
 
@@ -0,0 +1,393 @@
+//! Samplers hand out the random numbers a path consumes, one dimension at a
+//! time, so that the same dimension of different samples of a pixel can be
+//! spread evenly over the unit interval or square.
+
+use std::f32::consts::PI;
+
+use rand_pcg::Pcg64Mcg;
+
+use crate::prelude::{seeded_rng, vec3, Rng, Vec3, SAMPLER_STREAM};
+use crate::rng::{seed, splitmix64};
+
+// The largest f32 below one.
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+pub trait Sampler: Send + Sync {
+    /// Prepare to draw the dimensions of sample `index` of `pixel`, a linear
+    /// pixel index.
+    fn start_pixel_sample(&mut self, pixel: u64, index: u64);
+
+    /// The next dimension, in [0, 1).
+    fn get_1d(&mut self) -> f32;
+
+    /// The next two dimensions, in [0, 1)².
+    fn get_2d(&mut self) -> (f32, f32);
+
+    fn clone_box(&self) -> Box<dyn Sampler>;
+}
+
+// A 32-bit hash of `pixel` and `dim`, also keyed on the global seed.
+fn hash(pixel: u64, dim: u64) -> u32 {
+    (splitmix64(seed() ^ splitmix64(pixel) ^ splitmix64(dim.wrapping_add(pixel))) >> 32) as u32
+}
+
+// The generator for sample `index` of `pixel`, on a different stream from the
+// one the tracer reseeds `thread_rng` with for the same sample.
+fn sample_rng(pixel: u64, index: u64) -> Pcg64Mcg {
+    seeded_rng(SAMPLER_STREAM | pixel, index)
+}
+
+fn to_unit(bits: u32) -> f32 {
+    (bits as f32 * (1.0 / 4_294_967_296.0)).min(ONE_MINUS_EPSILON)
+}
+
+/// Uniform random numbers, with no correlation between samples.
+#[derive(Clone)]
+pub struct IndependentSampler {
+    rng: Pcg64Mcg,
+}
+
+impl IndependentSampler {
+    pub fn new() -> Self {
+        IndependentSampler {
+            rng: sample_rng(0, 0),
+        }
+    }
+}
+
+impl Default for IndependentSampler {
+    fn default() -> Self {
+        IndependentSampler::new()
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, pixel: u64, index: u64) {
+        self.rng = sample_rng(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        self.rng.gen::<f32>()
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        (self.rng.gen::<f32>(), self.rng.gen::<f32>())
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+/// Kensler's hash-based permutation of `[0, len)`, picking element `i` of the
+/// permutation selected by `key`.
+fn permute(mut i: u32, len: u32, key: u32) -> u32 {
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= key;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= key >> 16;
+        i ^= (i & w) >> 4;
+        i ^= key >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= key >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | key >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < len {
+            break;
+        }
+    }
+    (i.wrapping_add(key)) % len
+}
+
+/// Jittered strata, visited in a different random order for every pixel and
+/// dimension. The strata only cover the pixel evenly when it receives exactly
+/// `samples_per_pixel` samples.
+#[derive(Clone)]
+pub struct StratifiedSampler {
+    samples: u32,
+    pixel: u64,
+    index: u32,
+    dim: u64,
+    rng: Pcg64Mcg,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: usize) -> Self {
+        StratifiedSampler {
+            samples: samples_per_pixel.max(1) as u32,
+            pixel: 0,
+            index: 0,
+            dim: 0,
+            rng: sample_rng(0, 0),
+        }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, pixel: u64, index: u64) {
+        self.pixel = pixel;
+        self.index = index as u32;
+        self.dim = 0;
+        self.rng = sample_rng(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let key = hash(self.pixel, self.dim);
+        self.dim += 1;
+        let stratum = permute(self.index % self.samples, self.samples, key);
+        ((stratum as f32 + self.rng.gen::<f32>()) / self.samples as f32).min(ONE_MINUS_EPSILON)
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        let key = hash(self.pixel, self.dim);
+        self.dim += 2;
+        // the squarest grid with at least one stratum per sample
+        let nx = (self.samples as f32).sqrt().ceil() as u32;
+        let ny = (self.samples + nx - 1) / nx;
+        let stratum = permute(self.index % (nx * ny), nx * ny, key);
+        let x = ((stratum % nx) as f32 + self.rng.gen::<f32>()) / nx as f32;
+        let y = ((stratum / nx) as f32 + self.rng.gen::<f32>()) / ny as f32;
+        (x.min(ONE_MINUS_EPSILON), y.min(ONE_MINUS_EPSILON))
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+fn radical_inverse(base: u32, mut i: u64) -> f32 {
+    let base = base as u64;
+    let inv_base = 1.0 / base as f64;
+    let mut digits = 0u64;
+    let mut scale = 1.0;
+    while i > 0 {
+        digits = digits * base + i % base;
+        scale *= inv_base;
+        i /= base;
+    }
+    ((digits as f64 * scale) as f32).min(ONE_MINUS_EPSILON)
+}
+
+/// The Halton sequence, one prime base per dimension, with each pixel and
+/// dimension shifted by a random toroidal offset. Dimensions beyond the
+/// table of primes fall back to independent random numbers.
+#[derive(Clone)]
+pub struct HaltonSampler {
+    pixel: u64,
+    index: u64,
+    dim: usize,
+    rng: Pcg64Mcg,
+}
+
+impl HaltonSampler {
+    pub fn new() -> Self {
+        HaltonSampler {
+            pixel: 0,
+            index: 0,
+            dim: 0,
+            rng: sample_rng(0, 0),
+        }
+    }
+}
+
+impl Default for HaltonSampler {
+    fn default() -> Self {
+        HaltonSampler::new()
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, pixel: u64, index: u64) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dim = 0;
+        self.rng = sample_rng(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let dim = self.dim;
+        self.dim += 1;
+        if dim >= PRIMES.len() {
+            return self.rng.gen::<f32>();
+        }
+        let x = radical_inverse(PRIMES[dim], self.index) + to_unit(hash(self.pixel, dim as u64));
+        (x - x.floor()).min(ONE_MINUS_EPSILON)
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        (self.get_1d(), self.get_1d())
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+// Burley's hash-based Owen scrambling, after Laine and Karras.
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+// The first two dimensions of the Sobol sequence, as 32-bit fractions.
+fn sobol(index: u32) -> (u32, u32) {
+    let x = index.reverse_bits();
+    let mut y = 0;
+    let mut v = 1u32 << 31;
+    let mut i = index;
+    while i != 0 {
+        if i & 1 != 0 {
+            y ^= v;
+        }
+        i >>= 1;
+        v ^= v >> 1;
+    }
+    (x, y)
+}
+
+/// The Sobol sequence with Owen scrambling, following Burley's "Practical
+/// Hash-based Owen Scrambling": every 1D or 2D draw uses the first dimensions
+/// of an independently shuffled and scrambled sequence.
+#[derive(Clone)]
+pub struct SobolSampler {
+    pixel: u64,
+    index: u32,
+    dim: u64,
+}
+
+impl SobolSampler {
+    pub fn new() -> Self {
+        SobolSampler {
+            pixel: 0,
+            index: 0,
+            dim: 0,
+        }
+    }
+
+    fn next_seed(&mut self) -> u32 {
+        let seed = hash(self.pixel, self.dim);
+        self.dim += 1;
+        seed
+    }
+}
+
+impl Default for SobolSampler {
+    fn default() -> Self {
+        SobolSampler::new()
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, pixel: u64, index: u64) {
+        self.pixel = pixel;
+        self.index = index as u32;
+        self.dim = 0;
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let seed = self.next_seed();
+        let index = nested_uniform_scramble(self.index, seed);
+        let (x, _) = sobol(index);
+        to_unit(nested_uniform_scramble(x, splitmix64(seed as u64) as u32))
+    }
+
+    fn get_2d(&mut self) -> (f32, f32) {
+        let seed = self.next_seed();
+        let index = nested_uniform_scramble(self.index, seed);
+        let (x, y) = sobol(index);
+        let mix = splitmix64(seed as u64);
+        (
+            to_unit(nested_uniform_scramble(x, mix as u32)),
+            to_unit(nested_uniform_scramble(y, (mix >> 32) as u32)),
+        )
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+/// Map the unit square onto the unit disk in the z = 0 plane, preserving
+/// relative areas and keeping neighboring points close.
+pub fn concentric_disk((u, v): (f32, f32)) -> Vec3 {
+    let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vec3::zeros();
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+    vec3![r * theta.cos(), r * theta.sin(), 0]
+}
+
+/// A unit direction about +z with density `cos(theta) / pi`, by projecting
+/// the concentric disk up onto the hemisphere.
+pub fn cosine_hemisphere(u: (f32, f32)) -> Vec3 {
+    let d = concentric_disk(u);
+    let z = (1.0 - d.x * d.x - d.y * d.y).max(0.0).sqrt();
+    vec3![d.x, d.y, z]
+}
+
+/// A unit direction with uniform density over the sphere.
+pub fn uniform_sphere((u, v): (f32, f32)) -> Vec3 {
+    let z = 1.0 - 2.0 * u;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * v;
+    vec3![r * phi.cos(), r * phi.sin(), z]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{reseed_thread_rng, thread_rng};
+
+    #[test]
+    fn independent_of_thread_rng() {
+        // a single stratum leaves the jitter as the generator's raw output
+        let samplers: Vec<Box<dyn Sampler>> = vec![
+            Box::new(IndependentSampler::new()),
+            Box::new(StratifiedSampler::new(1)),
+        ];
+        for mut sampler in samplers {
+            for &(pixel, index) in &[(0, 0), (7, 3), (12345, 255)] {
+                sampler.start_pixel_sample(pixel, index);
+                reseed_thread_rng(pixel, index);
+                let ours = (0..8).map(|_| sampler.get_1d()).collect::<Vec<_>>();
+                let theirs = (0..8)
+                    .map(|_| thread_rng().gen::<f32>())
+                    .collect::<Vec<_>>();
+                assert_ne!(ours, theirs);
+            }
+        }
+    }
+}
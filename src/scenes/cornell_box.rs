@@ -0,0 +1,96 @@
+use crate::prelude::{
+    vec3, CameraConfig, ConstantMedium, Cuboid, DiffuseLight, Hittable, Lambertian, Mat4, Quad,
+    Transform, Vec3,
+};
+use crate::scenes::{constant_sky, Scene};
+
+pub fn camera() -> CameraConfig {
+    CameraConfig {
+        look_from: vec3![278, 278, -800],
+        look_at: vec3![278, 278, 0],
+        vup: vec3![0, 1, 0],
+        vfov: 40.0,
+        aperture: 0.0,
+        focus_dist: 10.0,
+        time0: 0.0,
+        time1: 1.0,
+    }
+}
+
+pub fn scene() -> Scene {
+    Scene {
+        camera: camera(),
+        world: world(),
+        skybox: constant_sky(Vec3::zeros()),
+        lights: lights(),
+    }
+}
+
+/// The Cornell box with its two blocks replaced by smoke and fog.
+pub fn smoke_scene() -> Scene {
+    Scene {
+        camera: camera(),
+        world: smoke_world(),
+        skybox: constant_sky(Vec3::zeros()),
+        lights: lights(),
+    }
+}
+
+fn ceiling_light() -> Box<dyn Hittable + Sync> {
+    let light = DiffuseLight::new(vec3![15, 15, 15]);
+    Quad::xz(213.0, 343.0, 227.0, 332.0, 554.0, light).into_box()
+}
+
+pub fn lights() -> Vec<Box<dyn Hittable + Sync>> {
+    vec![ceiling_light()]
+}
+
+fn white() -> Box<Lambertian> {
+    Lambertian::new(vec3![0.73, 0.73, 0.73])
+}
+
+fn walls() -> Vec<Box<dyn Hittable + Sync>> {
+    let red = Lambertian::new(vec3![0.65, 0.05, 0.05]);
+    let green = Lambertian::new(vec3![0.12, 0.45, 0.15]);
+
+    vec![
+        Quad::yz(0.0, 555.0, 0.0, 555.0, 555.0, green).into_box(),
+        Quad::yz(0.0, 555.0, 0.0, 555.0, 0.0, red).into_box(),
+        ceiling_light(),
+        Quad::xz(0.0, 555.0, 0.0, 555.0, 0.0, white()).into_box(),
+        Quad::xz(0.0, 555.0, 0.0, 555.0, 555.0, white()).into_box(),
+        Quad::xy(0.0, 555.0, 0.0, 555.0, 555.0, white()).into_box(),
+    ]
+}
+
+// the tall and short blocks, rotated into place
+fn blocks() -> [Box<dyn Hittable + Sync>; 2] {
+    [
+        Transform::new(
+            Cuboid::new(vec3![0, 0, 0], vec3![165, 330, 165], white()).into_box(),
+            Mat4::translate(vec3![265, 0, 295]) * Mat4::rotate_y(15.0),
+        )
+        .into_box(),
+        Transform::new(
+            Cuboid::new(vec3![0, 0, 0], vec3![165, 165, 165], white()).into_box(),
+            Mat4::translate(vec3![130, 0, 65]) * Mat4::rotate_y(-18.0),
+        )
+        .into_box(),
+    ]
+}
+
+pub fn world() -> Vec<Box<dyn Hittable + Sync>> {
+    let [tall, short] = blocks();
+    let mut world = walls();
+    world.push(tall);
+    world.push(short);
+    world
+}
+
+pub fn smoke_world() -> Vec<Box<dyn Hittable + Sync>> {
+    let [tall, short] = blocks();
+    let mut world = walls();
+    world.push(ConstantMedium::new(tall, 0.01, Vec3::zeros()).into_box());
+    world.push(ConstantMedium::new(short, 0.01, Vec3::ones()).into_box());
+    world
+}
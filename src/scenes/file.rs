@@ -0,0 +1,539 @@
+//! A line-oriented text format for describing scenes.
+//!
+//! Each non-blank line is a directive followed by whitespace-separated
+//! arguments; `#` starts a comment. Vectors and colors are three numbers.
+//!
+//! ```text
+//! camera look_from 13 2 3 look_at 0 0 0 vfov 20 aperture 0.1 focus_dist 10
+//! sky gradient 1 1 1 0.5 0.7 1.0
+//!
+//! texture checks checker 1.0 0.2 0.3 0.1 0.9 0.9 0.9
+//! material ground lambertian checks
+//! material gold metal 0.8 0.6 0.2 0.05
+//! material glass dielectric 1.5
+//! material lamp diffuse_light 4 4 4
+//!
+//! sphere 0 -1000 0 1000 ground
+//! sphere 0 1 0 1 glass
+//! moving_sphere 4 1 0 4 1.5 0 0 1 1 gold
+//! ```
+//!
+//! Besides spheres, objects are `quad <corner> <u> <v> <material>`, the
+//! parallelogram spanned by `u` and `v`, and `box <corner> <corner> <material>`,
+//! an axis-aligned box. `mesh <path.obj> [material]` loads a Wavefront OBJ file, relative to the
+//! scene file, optionally replacing its MTL materials with a declared one.
+//!
+//! `medium <density> <albedo> sphere <center> <radius>` and
+//! `medium <density> <albedo> box <corner> <corner>` fill a sphere or box with
+//! a constant-density volume that scatters isotropically, like smoke or fog.
+//!
+//! Untransformed spheres and quads of a `diffuse_light` material are also
+//! sampled directly as lights.
+//!
+//! `transform` places every following object: its arguments are any sequence
+//! of `translate <offset>`, `rotate_x|rotate_y|rotate_z <degrees>` and
+//! `scale <factors>`, applied to the object in the order written. A bare
+//! `transform` resets it.
+//!
+//! `camera` takes any of `look_from`, `look_at`, `vup`, `vfov`, `aperture`,
+//! `focus_dist` and `time t0 t1`; unspecified keys keep their defaults. `sky`
+//! is either `gradient <bottom> <top>` or `constant <color>`.
+//!
+//! Wherever a material or texture takes a color, the name of a declared
+//! texture may be given instead. Textures are `checker <size> <odd> <even>` and
+//! `gradient <start> <end> <color0> <color1>`. Textures and materials must be
+//! declared before they are used.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::SplitWhitespace;
+use std::sync::Arc;
+
+use failure::{format_err, Error};
+
+use crate::obj::load_obj;
+use crate::prelude::{
+    vec3, CameraConfig, Checker, ConstantMedium, Cuboid, Dielectric, DiffuseLight, Gradient,
+    Hittable, Lambertian, Mat4, Material, Metal, MovingSphere, Quad, Sphere, Texture, Transform,
+    Vec3,
+};
+use crate::scenes::{constant_sky, gradient_sky, Scene, Skybox};
+
+#[derive(Clone, Debug)]
+enum TextureSpec {
+    Constant(Vec3),
+    Checker(f32, Box<TextureSpec>, Box<TextureSpec>),
+    Gradient(Vec3, Vec3, Vec3, Vec3),
+}
+
+impl TextureSpec {
+    fn build(&self) -> Box<dyn Texture> {
+        match self {
+            TextureSpec::Constant(color) => Box::new(*color),
+            TextureSpec::Checker(size, odd, even) => {
+                Box::new(Checker::new(*size, odd.build(), even.build()))
+            }
+            TextureSpec::Gradient(start, end, color0, color1) => {
+                Box::new(Gradient::new(*start, *end, *color0, *color1))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum MaterialSpec {
+    Lambertian(TextureSpec),
+    Metal(TextureSpec, f32),
+    Dielectric(f32),
+    DiffuseLight(TextureSpec),
+}
+
+impl MaterialSpec {
+    fn build(&self) -> Box<dyn Material> {
+        match self {
+            MaterialSpec::Lambertian(albedo) => Lambertian::new(albedo.build()),
+            MaterialSpec::Metal(albedo, fuzz) => Metal::new(albedo.build(), *fuzz),
+            MaterialSpec::Dielectric(refractive_index) => Dielectric::new(*refractive_index),
+            MaterialSpec::DiffuseLight(emit) => DiffuseLight::new(emit.build()),
+        }
+    }
+}
+
+// A declared material, built once so that every object naming it shares one
+// instance, and with it one material id.
+#[derive(Clone)]
+struct NamedMaterial {
+    spec: MaterialSpec,
+    shared: Arc<dyn Material>,
+}
+
+impl NamedMaterial {
+    fn instance(&self) -> Box<dyn Material> {
+        Box::new(self.shared.clone())
+    }
+}
+
+struct Line<'a> {
+    number: usize,
+    tokens: SplitWhitespace<'a>,
+}
+
+impl<'a> Line<'a> {
+    fn error(&self, msg: String) -> Error {
+        format_err!("line {}: {}", self.number, msg)
+    }
+
+    fn word(&mut self, what: &str) -> Result<&'a str, Error> {
+        let number = self.number;
+        self.tokens
+            .next()
+            .ok_or_else(|| format_err!("line {}: expected {}", number, what))
+    }
+
+    fn float(&mut self, what: &str) -> Result<f32, Error> {
+        let token = self.word(what)?;
+        token
+            .parse::<f32>()
+            .map_err(|_| self.error(format!("expected {} but got '{}'", what, token)))
+    }
+
+    fn vec3(&mut self, what: &str) -> Result<Vec3, Error> {
+        Ok(vec3![
+            self.float(what)?,
+            self.float(what)?,
+            self.float(what)?
+        ])
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.clone().next()
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        match self.tokens.next() {
+            Some(token) => Err(self.error(format!("unexpected '{}'", token))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn default_camera() -> CameraConfig {
+    CameraConfig {
+        look_from: vec3![0, 0, 0],
+        look_at: vec3![0, 0, -1],
+        vup: vec3![0, 1, 0],
+        vfov: 90.0,
+        aperture: 0.0,
+        focus_dist: 1.0,
+        time0: 0.0,
+        time1: 1.0,
+    }
+}
+
+struct Parser {
+    base: PathBuf,
+    camera: CameraConfig,
+    skybox: Skybox,
+    textures: HashMap<String, TextureSpec>,
+    materials: HashMap<String, NamedMaterial>,
+    world: Vec<Box<dyn Hittable + Sync>>,
+    lights: Vec<Box<dyn Hittable + Sync>>,
+    transform: Option<Mat4>,
+}
+
+impl Parser {
+    fn push(&mut self, object: Box<dyn Hittable + Sync>) {
+        match self.transform {
+            Some(matrix) => self.world.push(Transform::new(object, matrix).into_box()),
+            None => self.world.push(object),
+        }
+    }
+
+    fn transform(&mut self, line: &mut Line) -> Result<(), Error> {
+        let mut matrix = None;
+        while let Some(op) = line.tokens.next() {
+            let step = match op {
+                "translate" => Mat4::translate(line.vec3("offset")?),
+                "rotate_x" => Mat4::rotate_x(line.float("degrees")?),
+                "rotate_y" => Mat4::rotate_y(line.float("degrees")?),
+                "rotate_z" => Mat4::rotate_z(line.float("degrees")?),
+                "scale" => Mat4::scale(line.vec3("factors")?),
+                _ => return Err(line.error(format!("unknown transform '{}'", op))),
+            };
+            matrix = Some(step * matrix.unwrap_or_else(Mat4::identity));
+        }
+        if let Some(m) = matrix {
+            if m.inverse().is_none() {
+                return Err(line.error("transform is singular".to_string()));
+            }
+        }
+        self.transform = matrix;
+        Ok(())
+    }
+
+    fn material(&self, line: &mut Line) -> Result<Box<dyn Material>, Error> {
+        self.named_material(line).map(|named| named.instance())
+    }
+
+    fn named_material(&self, line: &mut Line) -> Result<NamedMaterial, Error> {
+        let name = line.word("material name")?;
+        self.materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| line.error(format!("undefined material '{}'", name)))
+    }
+
+    // whether an object made of `named` should also be sampled as a light
+    fn is_light(&self, named: &NamedMaterial) -> bool {
+        matches!(named.spec, MaterialSpec::DiffuseLight(_)) && self.transform.is_none()
+    }
+
+    // either a literal color or the name of a declared texture
+    fn texture(&self, line: &mut Line, what: &str) -> Result<TextureSpec, Error> {
+        match line.peek() {
+            Some(token) if token.parse::<f32>().is_err() => {
+                line.tokens.next();
+                self.textures
+                    .get(token)
+                    .cloned()
+                    .ok_or_else(|| line.error(format!("undefined texture '{}'", token)))
+            }
+            _ => Ok(TextureSpec::Constant(line.vec3(what)?)),
+        }
+    }
+
+    fn define_texture(&mut self, line: &mut Line) -> Result<(), Error> {
+        let name = line.word("texture name")?;
+        let spec = match line.word("texture kind")? {
+            "checker" => {
+                let size = line.float("size")?;
+                if size <= 0.0 {
+                    return Err(line.error("checker size must be positive".to_string()));
+                }
+                TextureSpec::Checker(
+                    size,
+                    Box::new(self.texture(line, "odd color")?),
+                    Box::new(self.texture(line, "even color")?),
+                )
+            }
+            "gradient" => {
+                let start = line.vec3("start")?;
+                let end = line.vec3("end")?;
+                if start == end {
+                    return Err(line.error("gradient start and end coincide".to_string()));
+                }
+                TextureSpec::Gradient(start, end, line.vec3("color0")?, line.vec3("color1")?)
+            }
+            kind => return Err(line.error(format!("unknown texture kind '{}'", kind))),
+        };
+        self.textures.insert(name.to_string(), spec);
+        Ok(())
+    }
+
+    fn camera(&mut self, line: &mut Line) -> Result<(), Error> {
+        while let Some(key) = line.tokens.next() {
+            match key {
+                "look_from" => self.camera.look_from = line.vec3("look_from")?,
+                "look_at" => self.camera.look_at = line.vec3("look_at")?,
+                "vup" => self.camera.vup = line.vec3("vup")?,
+                "vfov" => self.camera.vfov = line.float("vfov")?,
+                "aperture" => self.camera.aperture = line.float("aperture")?,
+                "focus_dist" => self.camera.focus_dist = line.float("focus_dist")?,
+                "time" => {
+                    self.camera.time0 = line.float("time0")?;
+                    self.camera.time1 = line.float("time1")?;
+                }
+                _ => return Err(line.error(format!("unknown camera parameter '{}'", key))),
+            }
+        }
+        Ok(())
+    }
+
+    fn sky(&mut self, line: &mut Line) -> Result<(), Error> {
+        self.skybox = match line.word("sky kind")? {
+            "gradient" => gradient_sky(line.vec3("bottom color")?, line.vec3("top color")?),
+            "constant" => constant_sky(line.vec3("color")?),
+            kind => return Err(line.error(format!("unknown sky '{}'", kind))),
+        };
+        Ok(())
+    }
+
+    fn define_material(&mut self, line: &mut Line) -> Result<(), Error> {
+        let name = line.word("material name")?;
+        let spec = match line.word("material kind")? {
+            "lambertian" => MaterialSpec::Lambertian(self.texture(line, "albedo")?),
+            "metal" => MaterialSpec::Metal(self.texture(line, "albedo")?, line.float("fuzz")?),
+            "dielectric" => MaterialSpec::Dielectric(line.float("refractive index")?),
+            "diffuse_light" => MaterialSpec::DiffuseLight(self.texture(line, "emission")?),
+            kind => return Err(line.error(format!("unknown material kind '{}'", kind))),
+        };
+        let shared = Arc::from(spec.build());
+        self.materials
+            .insert(name.to_string(), NamedMaterial { spec, shared });
+        Ok(())
+    }
+
+    fn line(&mut self, line: &mut Line) -> Result<(), Error> {
+        match line.word("directive")? {
+            "camera" => self.camera(line)?,
+            "sky" => self.sky(line)?,
+            "transform" => self.transform(line)?,
+            "texture" => self.define_texture(line)?,
+            "material" => self.define_material(line)?,
+            "sphere" => {
+                let center = line.vec3("center")?;
+                let radius = line.float("radius")?;
+                let named = self.named_material(line)?;
+                if self.is_light(&named) {
+                    let light = Sphere::new(center, radius, named.instance());
+                    self.lights.push(light.into_box());
+                }
+                let material = named.instance();
+                self.push(Sphere::new(center, radius, material).into_box());
+            }
+            "moving_sphere" => {
+                let center0 = line.vec3("center0")?;
+                let center1 = line.vec3("center1")?;
+                let time0 = line.float("time0")?;
+                let time1 = line.float("time1")?;
+                if time0 == time1 {
+                    return Err(line.error("moving sphere times coincide".to_string()));
+                }
+                let radius = line.float("radius")?;
+                let material = self.material(line)?;
+                self.push(
+                    MovingSphere::new(center0, center1, time0, time1, radius, material).into_box(),
+                );
+            }
+            "quad" => {
+                let q = line.vec3("corner")?;
+                let u = line.vec3("u")?;
+                let v = line.vec3("v")?;
+                if u.cross(&v).squared_len() == 0.0 {
+                    return Err(line.error("quad edges are parallel".to_string()));
+                }
+                let named = self.named_material(line)?;
+                if self.is_light(&named) {
+                    self.lights
+                        .push(Quad::new(q, u, v, named.instance()).into_box());
+                }
+                let material = named.instance();
+                self.push(Quad::new(q, u, v, material).into_box());
+            }
+            "box" => {
+                let a = line.vec3("corner")?;
+                let b = line.vec3("opposite corner")?;
+                let material = self.material(line)?;
+                self.push(Cuboid::new(a, b, material).into_box());
+            }
+            "medium" => {
+                let density = line.float("density")?;
+                if density <= 0.0 {
+                    return Err(line.error("medium density must be positive".to_string()));
+                }
+                let albedo = self.texture(line, "albedo")?.build();
+                // the boundary's material is never shaded
+                let boundary = match line.word("medium boundary")? {
+                    "sphere" => {
+                        let center = line.vec3("center")?;
+                        let radius = line.float("radius")?;
+                        Sphere::new(center, radius, Lambertian::new(Vec3::zeros())).into_box()
+                    }
+                    "box" => {
+                        let a = line.vec3("corner")?;
+                        let b = line.vec3("opposite corner")?;
+                        Cuboid::new(a, b, Lambertian::new(Vec3::zeros())).into_box()
+                    }
+                    shape => return Err(line.error(format!("unknown medium boundary '{}'", shape))),
+                };
+                self.push(ConstantMedium::new(boundary, density, albedo).into_box());
+            }
+            "mesh" => {
+                let path = self.base.join(line.word("mesh path")?);
+                let mut mesh = load_obj(&path).map_err(|e| line.error(e.to_string()))?;
+                if line.peek().is_some() {
+                    mesh = mesh.with_material(self.material(line)?);
+                }
+                if let Some(matrix) = self.transform {
+                    mesh = mesh.transformed(&matrix);
+                }
+                self.world.extend(mesh.into_triangles());
+            }
+            directive => return Err(line.error(format!("unknown directive '{}'", directive))),
+        }
+        line.finish()
+    }
+}
+
+/// Parse a scene description, resolving mesh paths against the working directory.
+pub fn parse(src: &str) -> Result<Scene, Error> {
+    parse_in(src, Path::new(""))
+}
+
+fn parse_in(src: &str, base: &Path) -> Result<Scene, Error> {
+    let mut parser = Parser {
+        base: base.to_path_buf(),
+        camera: default_camera(),
+        skybox: gradient_sky(Vec3::ones(), vec3![0.5, 0.7, 1.0]),
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        world: vec![],
+        lights: vec![],
+        transform: None,
+    };
+    for (i, text) in src.lines().enumerate() {
+        let text = text.split('#').next().unwrap_or("");
+        if text.trim().is_empty() {
+            continue;
+        }
+        let mut line = Line {
+            number: i + 1,
+            tokens: text.split_whitespace(),
+        };
+        parser.line(&mut line)?;
+    }
+    if parser.world.is_empty() {
+        return Err(format_err!("scene contains no objects"));
+    }
+    Ok(Scene {
+        camera: parser.camera,
+        world: parser.world,
+        skybox: parser.skybox,
+        lights: parser.lights,
+    })
+}
+
+/// Read and parse a scene description from `path`.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Scene, Error> {
+    let path = path.as_ref();
+    let src = fs::read_to_string(path)
+        .map_err(|e| format_err!("failed to read {}: {}", path.display(), e))?;
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_in(&src, base).map_err(|e| format_err!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::Ray;
+
+    fn error(src: &str) -> String {
+        match parse(src) {
+            Ok(_) => panic!("expected an error parsing {:?}", src),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn errors_report_line_numbers() {
+        let header = "# a comment\n\nmaterial m lambertian 0.5 0.5 0.5\n";
+        let cases = [
+            (
+                "sphere 0 0 0 1 missing",
+                "line 4: undefined material 'missing'",
+            ),
+            (
+                "sphere 0 0 zero 1 m",
+                "line 4: expected center but got 'zero'",
+            ),
+            ("sphere 0 0 0 1 m extra", "line 4: unexpected 'extra'"),
+            ("cylinder 0 0 0 1 m", "line 4: unknown directive 'cylinder'"),
+            ("sphere 0 0 0", "line 4: expected radius"),
+            (
+                "texture t checker 0 1 1 1 0 0 0",
+                "line 4: checker size must be positive",
+            ),
+            (
+                "texture t gradient 1 1 1 1 1 1 0 0 0 1 1 1",
+                "line 4: gradient start and end coincide",
+            ),
+            (
+                "moving_sphere 0 0 0 1 0 0 0.5 0.5 1 m",
+                "line 4: moving sphere times coincide",
+            ),
+            (
+                "quad 0 0 0 1 0 0 2 0 0 m",
+                "line 4: quad edges are parallel",
+            ),
+            ("transform scale 1 0 1", "line 4: transform is singular"),
+            (
+                "medium 0 0.5 0.5 0.5 sphere 0 0 0 1",
+                "line 4: medium density must be positive",
+            ),
+        ];
+        for (text, expected) in cases.iter() {
+            assert_eq!(error(&format!("{}{}", header, text)), *expected);
+        }
+    }
+
+    #[test]
+    fn named_materials_share_an_id() {
+        let scene = parse(
+            "material gold metal 0.8 0.6 0.2 0.05\n\
+             material clay lambertian 0.5 0.4 0.3\n\
+             sphere -2 0 0 0.5 gold\n\
+             sphere 2 0 0 0.5 gold\n\
+             sphere 0 0 0 0.5 clay\n",
+        )
+        .unwrap();
+        let ids = [-2.0, 2.0, 0.0]
+            .iter()
+            .map(|&x| {
+                let r = Ray::new(vec3![x, 0, 5], vec3![0, 0, -1], 0.0);
+                let hits = scene.world.iter().filter_map(|o| o.hit(&r, 1e-3, f32::MAX));
+                hits.map(|hit| hit.material.id()).next().unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ids[0], ids[1]);
+        assert_ne!(ids[0], ids[2]);
+    }
+
+    #[test]
+    fn empty_scene() {
+        assert_eq!(
+            error("material m dielectric 1.5"),
+            "scene contains no objects"
+        );
+    }
+}
@@ -1 +1,41 @@
+use crate::prelude::{CameraConfig, Hittable, Ray, Vec3};
+
+pub mod cornell_box;
+pub mod file;
 pub mod random_spheres;
+
+pub type Skybox = Box<dyn Fn(&Ray) -> Vec3 + Sync>;
+
+/// A sky blending from `bottom` straight down to `top` straight up.
+pub fn gradient_sky(bottom: Vec3, top: Vec3) -> Skybox {
+    Box::new(move |r: &Ray| {
+        let t = 0.5 * (r.direction.as_unit().y + 1.0);
+        (1.0 - t) * bottom + t * top
+    })
+}
+
+/// A sky of uniform radiance; black leaves emitters as the only light.
+pub fn constant_sky(color: Vec3) -> Skybox {
+    Box::new(move |_: &Ray| color)
+}
+
+/// Everything needed to construct a `Tracer`, less the output resolution.
+pub struct Scene {
+    pub camera: CameraConfig,
+    pub world: Vec<Box<dyn Hittable + Sync>>,
+    pub skybox: Skybox,
+    /// Copies of the emitters worth sampling directly; see `Tracer::set_lights`.
+    pub lights: Vec<Box<dyn Hittable + Sync>>,
+}
+
+/// The names accepted by `by_name`.
+pub const NAMES: &[&str] = &["random_spheres", "cornell_box", "cornell_smoke"];
+
+pub fn by_name(name: &str) -> Option<Scene> {
+    match name {
+        "random_spheres" => Some(random_spheres::scene()),
+        "cornell_box" => Some(cornell_box::scene()),
+        "cornell_smoke" => Some(cornell_box::smoke_scene()),
+        _ => None,
+    }
+}
@@ -1,6 +1,8 @@
 use crate::prelude::{
-    thread_rng, vec3, Dielectric, Hittable, Lambertian, Metal, MovingSphere, Ray, Rng, Sphere, Vec3,
+    seeded_rng, vec3, CameraConfig, Dielectric, Hittable, Lambertian, Metal, MovingSphere, Ray,
+    Rng, Sphere, Vec3, CAMERA_STREAM, SCENE_STREAM,
 };
+use crate::scenes::Scene;
 
 pub fn skybox(r: &Ray) -> Vec3 {
     let unit_direction = r.direction.as_unit();
@@ -8,8 +10,34 @@ pub fn skybox(r: &Ray) -> Vec3 {
     (1.0 - t) * Vec3::ones() + t * vec3![0.5, 0.7, 1.0]
 }
 
+/// A view from a random point on a sphere around the origin.
+pub fn camera() -> CameraConfig {
+    let mut rng = seeded_rng(CAMERA_STREAM, 0);
+    let mut randf = || rng.gen_range(-1f32, 1f32);
+    let look_from = 18.38 * vec3![randf(), randf().abs(), randf()].as_unit();
+    CameraConfig {
+        look_from,
+        look_at: vec3![0, 0, 0],
+        vup: vec3![0, 1, 0],
+        vfov: 20.0,
+        aperture: 0.0,
+        focus_dist: 10.0,
+        time0: 0.0,
+        time1: 1.0,
+    }
+}
+
+pub fn scene() -> Scene {
+    Scene {
+        camera: camera(),
+        world: world(),
+        skybox: Box::new(skybox),
+        lights: vec![],
+    }
+}
+
 pub fn world() -> Vec<Box<dyn Hittable + Sync>> {
-    let mut rng = thread_rng();
+    let mut rng = seeded_rng(SCENE_STREAM, 0);
     let mut randf = move || rng.gen::<f32>();
     let mut result = vec![Sphere::new(
         vec3![0, -1000, 0],
@@ -1,4 +1,15 @@
-use crate::prelude::{vec3, HitRecord, Hittable, Material, Ray, Vec3, AABB};
+use std::f32::consts::PI;
+
+use crate::prelude::{
+    random_unit_vector, vec3, HitRecord, Hittable, Material, Onb, Ray, Sampler, Vec3, AABB,
+};
+
+// Longitude and latitude of a point on the unit sphere, each in [0, 1].
+fn sphere_uv(p: Vec3) -> (f32, f32) {
+    let theta = (-p.y).clamp(-1.0, 1.0).acos();
+    let phi = (-p.z).atan2(p.x) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
 
 pub struct Sphere {
     pub center: Vec3,
@@ -16,6 +27,19 @@ impl Sphere {
     }
 }
 
+impl Sphere {
+    // the cosine of the half-angle the sphere subtends at `origin`, or `None`
+    // from inside
+    fn cos_theta_max(&self, origin: Vec3) -> Option<f32> {
+        let ratio = self.radius * self.radius / (self.center - origin).squared_len();
+        if ratio < 1.0 {
+            Some((1.0 - ratio).sqrt())
+        } else {
+            None
+        }
+    }
+}
+
 impl Hittable for Sphere {
     fn hit(&self, r: &Ray, tmin: f32, tmax: f32) -> Option<HitRecord<'_>> {
         let oc = r.origin - self.center;
@@ -40,7 +64,8 @@ impl Hittable for Sphere {
             t.map(|t| {
                 let p = r.point_at(t);
                 let normal = (p - self.center) / self.radius;
-                HitRecord::new(t, p, normal, self.material.as_ref())
+                let (u, v) = sphere_uv(normal);
+                HitRecord::new(t, p, normal, self.material.as_ref()).with_uv(u, v)
             })
         } else {
             None
@@ -57,6 +82,32 @@ impl Hittable for Sphere {
     fn into_box(self) -> Box<dyn Hittable + Sync> {
         Box::new(self) as Box<dyn Hittable + Sync>
     }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        if self
+            .hit(&Ray::new(origin, direction, 0.0), 1e-3, f32::MAX)
+            .is_none()
+        {
+            return 0.0;
+        }
+        match self.cos_theta_max(origin) {
+            // uniform over the cone of directions that see the sphere
+            Some(cos_theta_max) => 1.0 / (2.0 * PI * (1.0 - cos_theta_max)),
+            None => 1.0 / (4.0 * PI),
+        }
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let cos_theta_max = match self.cos_theta_max(origin) {
+            Some(cos_theta_max) => cos_theta_max,
+            None => return random_unit_vector(sampler),
+        };
+        let (u, v) = sampler.get_2d();
+        let z = 1.0 + u * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * v;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        Onb::from_w(self.center - origin).local(vec3![phi.cos() * r, phi.sin() * r, z])
+    }
 }
 
 pub struct MovingSphere {
@@ -92,6 +143,11 @@ impl MovingSphere {
         let x = (time.min(self.time1) - self.time0) / (self.time1 - self.time0);
         self.center0 + slope * x
     }
+
+    /// The velocity of the center while the sphere moves.
+    pub fn velocity(&self) -> Vec3 {
+        (self.center1 - self.center0) / (self.time1 - self.time0)
+    }
 }
 
 impl Hittable for MovingSphere {
@@ -118,7 +174,16 @@ impl Hittable for MovingSphere {
             t.map(|t| {
                 let p = r.point_at(t);
                 let normal = (p - self.center(r.time)) / self.radius;
+                let (u, v) = sphere_uv(normal);
+                // the center stops at `center1`
+                let velocity = if r.time < self.time1 {
+                    self.velocity()
+                } else {
+                    Vec3::zeros()
+                };
                 HitRecord::new(t, p, normal, self.material.as_ref())
+                    .with_uv(u, v)
+                    .with_velocity(velocity)
             })
         } else {
             None
@@ -0,0 +1,78 @@
+use crate::prelude::Vec3;
+
+pub trait Texture: Sync + Send {
+    /// The color at surface coordinates `(u, v)` and world position `p`.
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> Vec3;
+}
+
+/// A `Vec3` is a texture of constant color.
+impl Texture for Vec3 {
+    fn value(&self, _u: f32, _v: f32, _p: &Vec3) -> Vec3 {
+        *self
+    }
+}
+
+impl<T: Texture + ?Sized> Texture for Box<T> {
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> Vec3 {
+        (**self).value(u, v, p)
+    }
+}
+
+/// Alternates between two textures on a 3D lattice of cubes.
+pub struct Checker {
+    size: f32,
+    odd: Box<dyn Texture>,
+    even: Box<dyn Texture>,
+}
+
+impl Checker {
+    pub fn new<T: Texture + 'static, U: Texture + 'static>(size: f32, odd: T, even: U) -> Self {
+        Checker {
+            size,
+            odd: Box::new(odd),
+            even: Box::new(even),
+        }
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> Vec3 {
+        let cell =
+            (p.x / self.size).floor() + (p.y / self.size).floor() + (p.z / self.size).floor();
+        if cell as i64 % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// Blends linearly between two colors along the segment from `start` to `end`,
+/// holding each color beyond its end. If `start` and `end` coincide, it is
+/// `color0` everywhere.
+pub struct Gradient {
+    start: Vec3,
+    axis: Vec3,
+    color0: Vec3,
+    color1: Vec3,
+}
+
+impl Gradient {
+    pub fn new(start: Vec3, end: Vec3, color0: Vec3, color1: Vec3) -> Self {
+        let axis = end - start;
+        let len2 = axis.squared_len();
+        Gradient {
+            start,
+            axis: if len2 > 0.0 { axis / len2 } else { axis },
+            color0,
+            color1,
+        }
+    }
+}
+
+impl Texture for Gradient {
+    fn value(&self, _u: f32, _v: f32, p: &Vec3) -> Vec3 {
+        let t = (*p - self.start).dot(&self.axis).clamp(0.0, 1.0);
+        (1.0 - t) * self.color0 + t * self.color1
+    }
+}
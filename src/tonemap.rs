@@ -0,0 +1,225 @@
+//! The transform from linear scene radiance to encoded display values.
+//!
+//! Radiance is scaled by the exposure, compressed into the displayable range
+//! by a tone-mapping operator, and encoded with the sRGB transfer function.
+
+use crate::prelude::{vec3, Vec3};
+
+/// An operator compressing linear radiance into [0, 1].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMap {
+    /// Clip each channel at one.
+    Clamp,
+    /// `L / (1 + L)` on luminance, which never quite reaches white.
+    Reinhard,
+    /// Reinhard's operator with luminance `white` and above mapped to white.
+    /// `white` must be positive.
+    ExtendedReinhard { white: f32 },
+    /// Stephen Hill's fit of the ACES reference rendering and sRGB output
+    /// transforms.
+    Aces,
+    /// Troy Sobotka's AgX, a filmic curve that desaturates bright colors
+    /// towards white rather than skewing their hue.
+    Agx,
+}
+
+/// Exposure, tone mapping and sRGB encoding applied to radiance for display.
+#[derive(Clone, Copy, Debug)]
+pub struct OutputTransform {
+    /// Exposure compensation in stops; each one doubles the radiance.
+    pub exposure: f32,
+    pub tone_map: ToneMap,
+}
+
+impl Default for OutputTransform {
+    fn default() -> Self {
+        OutputTransform {
+            exposure: 0.0,
+            tone_map: ToneMap::Clamp,
+        }
+    }
+}
+
+impl OutputTransform {
+    pub fn new(exposure: f32, tone_map: ToneMap) -> Self {
+        OutputTransform { exposure, tone_map }
+    }
+
+    /// Encoded sRGB values in [0, 1] for linear radiance `v`.
+    pub fn apply(&self, v: Vec3) -> Vec3 {
+        let v = v * self.exposure.exp2();
+        let v = match self.tone_map {
+            ToneMap::Clamp => v,
+            ToneMap::Reinhard => scale_luminance(v, |l| l / (1.0 + l)),
+            ToneMap::ExtendedReinhard { white } => {
+                scale_luminance(v, |l| l * (1.0 + l / (white * white)) / (1.0 + l))
+            }
+            ToneMap::Aces => aces_fitted(v),
+            ToneMap::Agx => agx(v),
+        };
+        vec3![
+            srgb_oetf(v.x.clamp(0.0, 1.0)),
+            srgb_oetf(v.y.clamp(0.0, 1.0)),
+            srgb_oetf(v.z.clamp(0.0, 1.0))
+        ]
+    }
+
+    /// The display pixel for linear radiance `v`.
+    pub fn to_argb(&self, v: Vec3) -> u32 {
+        self.apply(v).to_argb()
+    }
+}
+
+/// The sRGB encoding of a linear value in [0, 1].
+pub fn srgb_oetf(x: f32) -> f32 {
+    if x <= 0.003_130_8 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The linear value of an sRGB-encoded one; the inverse of `srgb_oetf`.
+pub fn srgb_eotf(x: f32) -> f32 {
+    if x <= 0.040_45 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Rec. 709 luminance of linear RGB.
+pub fn luminance(v: Vec3) -> f32 {
+    0.2126 * v.x + 0.7152 * v.y + 0.0722 * v.z
+}
+
+// Scale `v` so its luminance becomes `f` of its current luminance, keeping
+// the ratios between channels.
+fn scale_luminance<F: Fn(f32) -> f32>(v: Vec3, f: F) -> Vec3 {
+    let l = luminance(v);
+    if l > 0.0 {
+        v * (f(l) / l)
+    } else {
+        Vec3::zeros()
+    }
+}
+
+type Mat3 = [[f32; 3]; 3];
+
+fn mul(m: &Mat3, v: Vec3) -> Vec3 {
+    let row = |r: &[f32; 3]| r[0] * v.x + r[1] * v.y + r[2] * v.z;
+    vec3![row(&m[0]), row(&m[1]), row(&m[2])]
+}
+
+fn aces_fitted(v: Vec3) -> Vec3 {
+    // sRGB to the ACES rendering space, with the RRT's saturation applied
+    const INPUT: Mat3 = [
+        [0.59719, 0.35458, 0.04823],
+        [0.07600, 0.90834, 0.01566],
+        [0.02840, 0.13383, 0.83777],
+    ];
+    // back to linear sRGB after the ODT
+    const OUTPUT: Mat3 = [
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602],
+    ];
+    let curve = |x: f32| {
+        let a = x * (x + 0.024_578_6) - 0.000_090_537;
+        let b = x * (0.983_729 * x + 0.432_951) + 0.238_081;
+        a / b
+    };
+    let v = mul(&INPUT, v);
+    mul(&OUTPUT, vec3![curve(v.x), curve(v.y), curve(v.z)])
+}
+
+fn agx(v: Vec3) -> Vec3 {
+    // into AgX's working space, which pulls primaries slightly inwards
+    const INSET: Mat3 = [
+        [0.842_479_06, 0.078_433_6, 0.079_223_745],
+        [0.042_328_24, 0.878_468_6, 0.079_166_13],
+        [0.042_375_655, 0.078_433_6, 0.879_143],
+    ];
+    const OUTSET: Mat3 = [
+        [1.196_879, -0.098_020_88, -0.099_029_74],
+        [-0.052_896_85, 1.151_903_1, -0.098_961_18],
+        [-0.052_971_635, -0.098_043_45, 1.151_073_7],
+    ];
+    // the range of stops the curve spans
+    const MIN_EV: f32 = -12.473_93;
+    const MAX_EV: f32 = 4.026_069;
+    // a polynomial fit of the sigmoid, whose output is display-encoded
+    let curve = |x: f32| {
+        let x = (x.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+        let (x2, x4) = (x * x, x * x * x * x);
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.002_32
+    };
+    let v = mul(&INSET, v);
+    let v = mul(&OUTSET, vec3![curve(v.x), curve(v.y), curve(v.z)]);
+    // undo the curve's display encoding so the result is linear again
+    let linear = |x: f32| x.max(0.0).powf(2.2);
+    vec3![linear(v.x), linear(v.y), linear(v.z)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_matches_reference_values() {
+        let reference = [
+            (0.0, 0.0),
+            (0.001, 0.012_92),
+            (0.003_130_8, 0.040_45),
+            (0.18, 0.461_356),
+            (0.5, 0.735_357),
+            (1.0, 1.0),
+        ];
+        for &(linear, encoded) in reference.iter() {
+            assert!((srgb_oetf(linear) - encoded).abs() < 1e-5, "{}", linear);
+            assert!((srgb_eotf(encoded) - linear).abs() < 1e-5, "{}", encoded);
+        }
+    }
+
+    #[test]
+    fn tone_maps_are_monotonic() {
+        let tone_maps = [
+            ToneMap::Clamp,
+            ToneMap::Reinhard,
+            ToneMap::ExtendedReinhard { white: 4.0 },
+            ToneMap::Aces,
+            ToneMap::Agx,
+        ];
+        for &tone_map in tone_maps.iter() {
+            let transform = OutputTransform::new(0.0, tone_map);
+            // a gray and a saturated color, from far below to far above white
+            for &color in [vec3![1, 1, 1], vec3![1.0, 0.4, 0.1]].iter() {
+                let mut last = Vec3::zeros();
+                for i in 0..=400 {
+                    let x = 10f32.powf(-4.0 + i as f32 / 50.0);
+                    let v = transform.apply(x * color);
+                    assert!(v.x <= 1.0 && v.y <= 1.0 && v.z <= 1.0);
+                    if color == Vec3::ones() {
+                        for c in 0..3 {
+                            assert!(v[c] >= last[c], "{:?} at {}", tone_map, x);
+                        }
+                    } else {
+                        // AgX desaturates bright colors towards white, which
+                        // dims their clipped channels by a hair as the others
+                        // rise
+                        let tolerance = 1e-3;
+                        assert!(
+                            luminance(v) >= luminance(last) - tolerance,
+                            "{:?} at {}",
+                            tone_map,
+                            x
+                        );
+                    }
+                    last = v;
+                }
+                assert!(last.x > 0.8, "{:?} never approaches white", tone_map);
+            }
+        }
+    }
+}
@@ -1,31 +1,214 @@
 use rayon::prelude::*;
 
-use crate::prelude::{thread_rng, Camera, Hittable, Ray, Rng, Vec3, BVH};
+use std::collections::HashMap;
 
-const MAX_DEPTH: usize = 50;
+use crate::adaptive::{heat_color, AdaptiveOptions, PixelStats};
+use crate::denoise::denoise;
+use crate::prelude::{
+    reseed_thread_rng, vec3, Aov, BuildOptions, Camera, DenoiseOptions, Features, Filter, FirstHit,
+    FlatBVH, Hittable, IndependentSampler, OutputTransform, Ray, Sampler, ScatterRecord,
+    ThinLensCamera, Vec3, BVH, BVH_STREAM,
+};
 
-pub fn color<F>(r: &Ray, world: &BVH, skybox: F, depth: usize) -> Vec3
+/// Bounds on the length of a path, in bounces.
+#[derive(Clone, Copy, Debug)]
+pub struct DepthLimits {
+    /// Bounces of any kind.
+    pub max: usize,
+    /// Bounces that sample a diffuse lobe or a medium's phase function.
+    pub diffuse: usize,
+    /// Mirror-like reflections.
+    pub specular: usize,
+    /// Refractions through a surface.
+    pub transmission: usize,
+    /// Bounces before Russian roulette may end a path.
+    pub roulette: usize,
+}
+
+impl Default for DepthLimits {
+    fn default() -> Self {
+        DepthLimits {
+            max: 50,
+            diffuse: 50,
+            specular: 50,
+            transmission: 50,
+            roulette: 3,
+        }
+    }
+}
+
+// Paths always have at least this chance of surviving a roulette round,
+// which keeps their weights bounded, and never more than `MAX_SURVIVAL`,
+// which ends paths that lose no energy, such as between parallel mirrors.
+const MIN_SURVIVAL: f32 = 0.05;
+const MAX_SURVIVAL: f32 = 0.95;
+
+// The power heuristic weight of a sample drawn with density `a`, when `b` is
+// the density the other strategy would have drawn it with.
+fn power_heuristic(a: f32, b: f32) -> f32 {
+    let (a2, b2) = (a * a, b * b);
+    if a2 + b2 > 0.0 {
+        a2 / (a2 + b2)
+    } else {
+        0.0
+    }
+}
+
+/// The radiance arriving along `r`.
+///
+/// At every diffuse vertex a shadow ray is cast towards a point sampled on
+/// `lights`, and combined with the BSDF-sampled continuation by multiple
+/// importance sampling. Scattering events inside a `ConstantMedium` arrive as
+/// ordinary hits whose phase-function material continues the path from
+/// within the volume.
+///
+/// What the ray hits first is recorded in `first_hit`, if given.
+///
+/// Once a path is `limits.roulette` bounces long, it survives each further
+/// bounce with a probability that follows its throughput, and survivors are
+/// reweighted so the estimate stays unbiased.
+pub fn color<F>(
+    r: &Ray,
+    world: &FlatBVH,
+    lights: Option<&(dyn Hittable + Sync)>,
+    skybox: F,
+    limits: &DepthLimits,
+    sampler: &mut dyn Sampler,
+    first_hit: Option<&mut FirstHit>,
+) -> Vec3
 where
     F: Fn(&Ray) -> Vec3,
 {
-    if let Some(hit) = world.hit(r, 1e-3, std::f32::MAX) {
-        if depth >= MAX_DEPTH {
-            return Vec3::zeros();
-        }
-        return if let Some((attenuation, scattered)) = hit.material.scatter(r, &hit) {
-            attenuation * color(&scattered, world, skybox, depth + 1)
-        } else {
-            Vec3::zeros()
+    let mut radiance = Vec3::zeros();
+    let mut throughput = Vec3::ones();
+    let mut ray = *r;
+    // the density the last bounce was sampled with, or `None` if specular
+    let mut bsdf_pdf: Option<f32> = None;
+    let (mut diffuse, mut specular, mut transmission) = (0, 0, 0);
+    let mut first_hit = first_hit;
+
+    for depth in 0..=limits.max {
+        let hit = match world.hit(&ray, 1e-3, f32::MAX) {
+            Some(hit) => hit,
+            None => {
+                let sky = skybox(&ray);
+                if let Some(first) = first_hit.take() {
+                    first.albedo = sky;
+                }
+                radiance += throughput * sky;
+                break;
+            }
         };
+        if let Some(first) = first_hit.take() {
+            *first = FirstHit {
+                t: hit.t,
+                distance: hit.t * ray.direction.len(),
+                position: hit.p,
+                normal: hit.normal.as_unit(),
+                albedo: hit.material.albedo(&hit),
+                velocity: hit.velocity,
+                material: hit.material.id(),
+                object: hit.object_id + 1,
+            };
+        }
+
+        let emitted = hit.material.emitted(&ray, &hit);
+        if emitted.squared_len() > 0.0 {
+            // emitters the shadow rays could have found share the credit
+            let weight = match (bsdf_pdf, lights) {
+                (Some(bsdf_pdf), Some(lights)) => {
+                    power_heuristic(bsdf_pdf, lights.pdf_value(ray.origin, ray.direction))
+                }
+                _ => 1.0,
+            };
+            radiance += weight * throughput * emitted;
+        }
+        if depth == limits.max {
+            break;
+        }
+        if depth >= limits.roulette {
+            let survival = throughput
+                .x
+                .max(throughput.y)
+                .max(throughput.z)
+                .clamp(MIN_SURVIVAL, MAX_SURVIVAL);
+            if sampler.get_1d() >= survival {
+                break;
+            }
+            throughput /= survival;
+        }
+
+        match hit.material.scatter(&ray, &hit, sampler) {
+            Some(ScatterRecord::Specular {
+                attenuation,
+                ray: next,
+                transmitted,
+            }) => {
+                let (count, limit) = if transmitted {
+                    (&mut transmission, limits.transmission)
+                } else {
+                    (&mut specular, limits.specular)
+                };
+                if *count == limit {
+                    break;
+                }
+                *count += 1;
+                throughput = throughput * attenuation;
+                ray = next;
+                bsdf_pdf = None;
+            }
+            Some(ScatterRecord::Diffuse { pdf }) => {
+                if diffuse == limits.diffuse {
+                    break;
+                }
+                diffuse += 1;
+                if let Some(lights) = lights {
+                    let direction = lights.random(hit.p, sampler);
+                    let light_pdf = lights.pdf_value(hit.p, direction);
+                    let f = hit.material.eval(&ray, &hit, &direction);
+                    if light_pdf > 0.0 && f.squared_len() > 0.0 {
+                        let shadow = Ray::new(hit.p, direction, ray.time);
+                        if let Some(light_hit) = world.hit(&shadow, 1e-3, f32::MAX) {
+                            let emitted = light_hit.material.emitted(&shadow, &light_hit);
+                            let weight = power_heuristic(
+                                light_pdf,
+                                hit.material.pdf(&ray, &hit, &direction),
+                            );
+                            radiance += weight / light_pdf * throughput * f * emitted;
+                        }
+                    }
+                }
+
+                let direction = pdf.generate(sampler);
+                let pdf_value = pdf.value(&direction);
+                if pdf_value <= 0.0 {
+                    break;
+                }
+                throughput = throughput * hit.material.eval(&ray, &hit, &direction) / pdf_value;
+                ray = Ray::new(hit.p, direction, ray.time);
+                bsdf_pdf = Some(pdf_value);
+            }
+            None => break,
+        }
     }
-    skybox(r)
+    radiance
 }
 
-fn split_scanlines<'a>(
-    mut buffer: &'a mut [u32],
-    width: usize,
-    height: usize,
-) -> Vec<(usize, &'a mut [u32])> {
+// The distance in pixels a hit point moves across the image over the
+// shutter interval.
+fn motion(camera: &dyn Camera, first: &FirstHit, time: f32, width: f32, height: f32) -> Vec3 {
+    if first.object == 0 {
+        return Vec3::zeros();
+    }
+    let at = |t: f32| camera.project(first.position + first.velocity * (t - time));
+    let (time0, time1) = camera.shutter();
+    match (at(time0), at(time1)) {
+        (Some((s0, t0)), Some((s1, t1))) => vec3![(s1 - s0) * width, (t1 - t0) * height, 0.0],
+        _ => Vec3::zeros(),
+    }
+}
+
+fn split_scanlines<T>(mut buffer: &mut [T], width: usize, height: usize) -> Vec<(usize, &mut [T])> {
     if buffer.len() < width * height {
         panic!("rendering buffer is insufficiently sized");
     }
@@ -43,43 +226,610 @@ fn split_scanlines<'a>(
     res
 }
 
-pub struct Tracer<F: Fn(&Ray) -> Vec3 + Sync> {
-    camera: Camera,
-    world: BVH,
+// A camera sample at a film position, in pixels from the bottom left.
+// Converged pixels leave an inactive sample.
+#[derive(Clone, Copy, Default)]
+struct FilmSample {
+    active: bool,
+    x: f32,
+    y: f32,
+    radiance: Vec3,
+    time: f32,
+    first_hit: FirstHit,
+}
+
+// Per-pixel storage for a render pass.
+enum AovBuffer {
+    // sums over every sample
+    Sum(Vec<Vec3>),
+    // the `FirstHit` key of the first sample
+    Key(Vec<usize>),
+}
+
+// The filter-weighted sum of the samples around a pixel.
+#[derive(Clone, Copy, Default)]
+struct Pixel {
+    sum: Vec3,
+    weight: f32,
+}
+
+impl Pixel {
+    fn value(&self) -> Vec3 {
+        if self.weight > 0.0 {
+            self.sum / self.weight
+        } else {
+            Vec3::zeros()
+        }
+    }
+}
+
+// The range of pixel indices, below `n`, whose centers lie within `radius`
+// of `x`.
+fn footprint(x: f32, radius: f32, n: usize) -> std::ops::Range<usize> {
+    let lo = (x - radius - 0.5).ceil().max(0.0) as usize;
+    let hi = ((x + radius - 0.5).floor() + 1.0).clamp(0.0, n as f32) as usize;
+    lo..hi.max(lo)
+}
+
+pub struct Tracer<F: Fn(&Ray) -> Vec3 + Sync, C: Camera = ThinLensCamera> {
+    camera: C,
+    world: FlatBVH,
     skybox: F,
+    width: usize,
+    height: usize,
+    // filtered linear radiance, top row first
+    accum: Vec<Pixel>,
+    // the samples of the last pass, top row first
+    samples: Vec<FilmSample>,
+    filter: Filter,
+    output: OutputTransform,
+    // sums of the first-hit features of each pixel's samples, top row first
+    features: Vec<Features>,
+    denoiser: Option<DenoiseOptions>,
+    aovs: Vec<(Aov, AovBuffer)>,
+    adaptive: Option<AdaptiveOptions>,
+    // per-pixel luminance statistics and whether to keep sampling, top row
+    // first
+    stats: Vec<PixelStats>,
+    active: Vec<bool>,
+    n_samples: usize,
+    limits: DepthLimits,
+    // shapes sampled for direct lighting; duplicates of emitters in `world`
+    lights: Vec<Box<dyn Hittable + Sync>>,
+    // cloned for each scanline
+    sampler: Box<dyn Sampler>,
 }
 
-impl<F: Fn(&Ray) -> Vec3 + Sync> Tracer<F> {
-    pub fn new(camera: Camera, world: Vec<Box<dyn Hittable + Sync>>, skybox: F) -> Self {
-        let world = BVH::new(world, camera.time0, camera.time1);
-        Tracer {
+impl<F: Fn(&Ray) -> Vec3 + Sync, C: Camera> Tracer<F, C> {
+    pub fn new(
+        camera: C,
+        world: Vec<Box<dyn Hittable + Sync>>,
+        skybox: F,
+        width: usize,
+        height: usize,
+    ) -> Self {
+        Tracer::with_bvh_options(
             camera,
             world,
             skybox,
-        }
+            width,
+            height,
+            &BuildOptions::default(),
+        )
     }
 
-    pub fn render_sample(
-        &self,
-        buffer: &mut [u32],
+    /// Like `new`, but building the BVH with the given options.
+    pub fn with_bvh_options(
+        camera: C,
+        world: Vec<Box<dyn Hittable + Sync>>,
+        skybox: F,
         width: usize,
         height: usize,
-        n_samples: usize,
-    ) -> usize {
-        split_scanlines(buffer, width, height)
+        options: &BuildOptions,
+    ) -> Self {
+        reseed_thread_rng(BVH_STREAM, 0);
+        let (time0, time1) = camera.shutter();
+        let world = FlatBVH::new(BVH::build(world, time0, time1, options));
+        Tracer {
+            camera,
+            world,
+            skybox,
+            width,
+            height,
+            accum: vec![Pixel::default(); width * height],
+            samples: vec![FilmSample::default(); width * height],
+            filter: Filter::default(),
+            output: OutputTransform::default(),
+            features: vec![Features::default(); width * height],
+            denoiser: None,
+            aovs: vec![],
+            adaptive: None,
+            stats: vec![PixelStats::default(); width * height],
+            active: vec![true; width * height],
+            n_samples: 0,
+            limits: DepthLimits::default(),
+            lights: vec![],
+            sampler: Box::new(IndependentSampler::new()),
+        }
+    }
+
+    pub fn camera(&self) -> &C {
+        &self.camera
+    }
+
+    /// Look through `camera` instead, keeping the BVH and discarding the
+    /// samples so far. The BVH only bounds motion within the shutter interval
+    /// of the camera it was built for.
+    pub fn set_camera(&mut self, camera: C) {
+        self.camera = camera;
+        self.reset();
+    }
+
+    pub fn world(&self) -> &FlatBVH {
+        &self.world
+    }
+
+    pub fn depth_limits(&self) -> &DepthLimits {
+        &self.limits
+    }
+
+    /// Set the bounce counts after which paths are terminated.
+    pub fn set_depth_limits(&mut self, limits: DepthLimits) {
+        self.limits = limits;
+    }
+
+    /// Sample these shapes for direct lighting at every diffuse vertex. Each
+    /// should duplicate an emitter in the world; lights left out are still
+    /// found, just by chance.
+    pub fn set_lights(&mut self, lights: Vec<Box<dyn Hittable + Sync>>) {
+        self.lights = lights;
+    }
+
+    /// Draw the random numbers of every path from `sampler`. Images made with
+    /// different samplers are not comparable, so this discards the samples so
+    /// far.
+    pub fn set_sampler(&mut self, sampler: Box<dyn Sampler>) {
+        self.sampler = sampler;
+        self.reset();
+    }
+
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    /// Reconstruct pixels with `filter`, discarding the samples so far.
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+        self.reset();
+    }
+
+    pub fn output_transform(&self) -> &OutputTransform {
+        &self.output
+    }
+
+    /// Display the image through `output`. This only affects `to_argb`, so
+    /// the samples so far are kept.
+    pub fn set_output_transform(&mut self, output: OutputTransform) {
+        self.output = output;
+    }
+
+    pub fn denoiser(&self) -> Option<&DenoiseOptions> {
+        self.denoiser.as_ref()
+    }
+
+    /// Denoise the image returned by `image` and `to_argb` with these
+    /// options, or not at all if `None`. The samples so far are kept.
+    pub fn set_denoiser(&mut self, denoiser: Option<DenoiseOptions>) {
+        self.denoiser = denoiser;
+    }
+
+    /// Record these passes from now on, discarding the samples so far.
+    pub fn set_aovs(&mut self, aovs: &[Aov]) {
+        let n = self.width * self.height;
+        self.aovs = aovs
+            .iter()
+            .map(|&aov| {
+                let buffer = if aov.is_id() {
+                    AovBuffer::Key(vec![0; n])
+                } else {
+                    AovBuffer::Sum(vec![Vec3::zeros(); n])
+                };
+                (aov, buffer)
+            })
+            .collect();
+        self.reset();
+    }
+
+    pub fn adaptive(&self) -> Option<&AdaptiveOptions> {
+        self.adaptive.as_ref()
+    }
+
+    /// Stop sampling pixels once they converge by these criteria, or sample
+    /// every pixel on every pass if `None`.
+    pub fn set_adaptive(&mut self, adaptive: Option<AdaptiveOptions>) {
+        self.adaptive = adaptive;
+        self.update_active();
+    }
+
+    /// How many pixels the next pass will sample.
+    pub fn n_active(&self) -> usize {
+        self.active.par_iter().filter(|&&a| a).count()
+    }
+
+    /// The samples each pixel has received, top row first.
+    pub fn sample_counts(&self) -> Vec<usize> {
+        self.stats.iter().map(|s| s.count).collect()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn n_samples(&self) -> usize {
+        self.n_samples
+    }
+
+    /// Discard all accumulated samples.
+    pub fn reset(&mut self) {
+        self.accum.iter_mut().for_each(|p| *p = Pixel::default());
+        self.features
+            .iter_mut()
+            .for_each(|f| *f = Features::default());
+        for (_, buffer) in self.aovs.iter_mut() {
+            match buffer {
+                AovBuffer::Sum(sums) => sums.iter_mut().for_each(|v| *v = Vec3::zeros()),
+                AovBuffer::Key(keys) => keys.iter_mut().for_each(|k| *k = 0),
+            }
+        }
+        self.stats
+            .iter_mut()
+            .for_each(|s| *s = PixelStats::default());
+        self.active.iter_mut().for_each(|a| *a = true);
+        self.n_samples = 0;
+    }
+
+    /// Trace one more sample for every pixel that has not converged, returning
+    /// the number of passes so far.
+    ///
+    /// Each pixel sample draws from the sampler, and from its own random stream
+    /// for anything else, both keyed on the pixel and sample index, so the
+    /// image depends only on the global seed.
+    ///
+    /// Samples are traced a scanline at a time, then every pixel gathers the
+    /// samples within the filter radius of its center, so no two threads ever
+    /// write to the same pixel.
+    pub fn render_sample(&mut self) -> usize {
+        let (width, height) = (self.width, self.height);
+        let n_samples = self.n_samples as u64;
+        let camera = &self.camera;
+        let world = &self.world;
+        let skybox = &self.skybox;
+        let limits = &self.limits;
+        let prototype = &self.sampler;
+        let active = &self.active;
+        let lights = if self.lights.is_empty() {
+            None
+        } else {
+            Some(&self.lights as &(dyn Hittable + Sync))
+        };
+        split_scanlines(&mut self.samples, width, height)
             .into_par_iter()
             .for_each(|(y, block)| {
-                let mut rng = thread_rng();
+                let mut sampler = prototype.clone_box();
+                let sampler = sampler.as_mut();
+                let row = (height - 1 - y) * width;
                 (0..width).for_each(|x| {
-                    let mut avg = Vec3::from_argb(block[x]).powi(2) * n_samples as f32;
-                    let v = (y as f32 + rng.gen::<f32>()) / height as f32;
-                    let u = (x as f32 + rng.gen::<f32>()) / width as f32;
-                    let r = self.camera.get_ray(u, v);
-                    avg += color(&r, &self.world, &self.skybox, 0);
-                    avg /= (n_samples + 1) as f32;
-                    block[x] = avg.sqrt().to_argb();
+                    let pixel = (row + x) as u64;
+                    if !active[row + x] {
+                        block[x] = FilmSample::default();
+                        return;
+                    }
+                    reseed_thread_rng(pixel, n_samples);
+                    sampler.start_pixel_sample(pixel, n_samples);
+                    let (du, dv) = sampler.get_2d();
+                    let (fx, fy) = (x as f32 + du, y as f32 + dv);
+                    let r = camera.get_ray(fx / width as f32, fy / height as f32, sampler);
+                    let mut first_hit = FirstHit::default();
+                    let radiance = color(
+                        &r,
+                        world,
+                        lights,
+                        skybox,
+                        limits,
+                        sampler,
+                        Some(&mut first_hit),
+                    );
+                    block[x] = FilmSample {
+                        active: true,
+                        x: fx,
+                        y: fy,
+                        radiance,
+                        time: r.time,
+                        first_hit,
+                    };
                 });
             });
-        n_samples + 1
+        self.splat();
+        // features are a guide, so each sample only counts towards its own pixel
+        self.features
+            .par_iter_mut()
+            .zip(self.samples.par_iter())
+            .filter(|(_, sample)| sample.active)
+            .for_each(|(sum, sample)| {
+                sum.albedo += sample.first_hit.albedo;
+                sum.normal += sample.first_hit.normal;
+                sum.depth += sample.first_hit.distance;
+            });
+        self.accumulate_aovs();
+        self.stats
+            .par_iter_mut()
+            .zip(self.samples.par_iter())
+            .filter(|(_, sample)| sample.active)
+            .for_each(|(stats, sample)| stats.push(sample.radiance));
+        self.n_samples += 1;
+        self.update_active();
+        self.n_samples
+    }
+
+    // Decide which pixels the next pass samples. A pixel is done once it and
+    // its neighbours are all below the error threshold, so a few lucky
+    // samples alone don't stop it.
+    fn update_active(&mut self) {
+        let options = match self.adaptive {
+            Some(options) if self.n_samples >= options.min_samples => options,
+            Some(_) => return,
+            None => {
+                self.active.par_iter_mut().for_each(|a| *a = true);
+                return;
+            }
+        };
+        let (width, height) = (self.width, self.height);
+        let errors: Vec<f32> = self.stats.par_iter().map(|s| s.relative_error()).collect();
+        self.active
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, active) in row.iter_mut().enumerate() {
+                    let mut ys = y.saturating_sub(1)..(y + 2).min(height);
+                    *active = ys.any(|qy| {
+                        let mut xs = x.saturating_sub(1)..(x + 2).min(width);
+                        xs.any(|qx| errors[qy * width + qx] >= options.threshold)
+                    });
+                }
+            });
+    }
+
+    // Add the last pass to the render passes, each sample to its own pixel.
+    fn accumulate_aovs(&mut self) {
+        let first_pass = self.n_samples == 0;
+        let samples = &self.samples;
+        let camera = &self.camera;
+        let (width, height) = (self.width as f32, self.height as f32);
+        for (aov, buffer) in self.aovs.iter_mut() {
+            let aov = *aov;
+            match buffer {
+                AovBuffer::Sum(sums) => sums
+                    .par_iter_mut()
+                    .zip(samples.par_iter())
+                    .filter(|(_, sample)| sample.active)
+                    .for_each(|(sum, sample)| {
+                        let first = &sample.first_hit;
+                        *sum += match aov {
+                            Aov::Depth => vec3![first.t, first.t, first.t],
+                            Aov::Normal => first.normal,
+                            Aov::Position => first.position,
+                            Aov::Albedo if first.object > 0 => first.albedo,
+                            Aov::Motion => motion(&*camera, first, sample.time, width, height),
+                            _ => Vec3::zeros(),
+                        };
+                    }),
+                AovBuffer::Key(keys) if first_pass => keys
+                    .par_iter_mut()
+                    .zip(samples.par_iter())
+                    .for_each(|(key, sample)| {
+                        *key = match aov {
+                            Aov::MaterialId => sample.first_hit.material,
+                            _ => sample.first_hit.object,
+                        };
+                    }),
+                AovBuffer::Key(_) => {}
+            }
+        }
+    }
+
+    // Add the filtered samples of the last pass to the pixels.
+    fn splat(&mut self) {
+        let (width, height) = (self.width, self.height);
+        let filter = &self.filter;
+        let samples = &self.samples;
+        let active = &self.active;
+        split_scanlines(&mut self.accum, width, height)
+            .into_par_iter()
+            .for_each(|(y, block)| {
+                let cy = y as f32 + 0.5;
+                // converged pixels are frozen; their neighbours' samples would
+                // otherwise keep pulling them toward the neighbours' radiance
+                let first = (height - 1 - y) * width;
+                let active = &active[first..first + width];
+                for row in footprint(cy, filter.radius, height) {
+                    let start = (height - 1 - row) * width;
+                    for sample in samples[start..start + width].iter().filter(|s| s.active) {
+                        let wy = filter.evaluate_1d(sample.y - cy);
+                        if wy == 0.0 {
+                            continue;
+                        }
+                        for x in footprint(sample.x, filter.radius, width) {
+                            if !active[x] {
+                                continue;
+                            }
+                            let w = wy * filter.evaluate_1d(x as f32 + 0.5 - sample.x);
+                            block[x].sum += w * sample.radiance;
+                            block[x].weight += w;
+                        }
+                    }
+                }
+            });
+    }
+
+    /// The filtered linear radiance of every pixel, top row first.
+    pub fn radiance(&self) -> Vec<Vec3> {
+        self.accum.par_iter().map(Pixel::value).collect()
+    }
+
+    /// The mean first-hit features of every pixel, top row first.
+    pub fn features(&self) -> Vec<Features> {
+        self.features
+            .par_iter()
+            .zip(self.stats.par_iter())
+            .map(|(f, stats)| {
+                let scale = 1.0 / stats.count.max(1) as f32;
+                Features {
+                    albedo: f.albedo * scale,
+                    normal: if f.normal.squared_len() > 0.0 {
+                        f.normal.as_unit()
+                    } else {
+                        Vec3::zeros()
+                    },
+                    depth: f.depth * scale,
+                }
+            })
+            .collect()
+    }
+
+    /// The values of a render pass set with `set_aovs`, top row first,
+    /// averaged over each pixel's samples. `None` if the pass isn't recorded.
+    pub fn aov(&self, aov: Aov) -> Option<Vec<Vec3>> {
+        let (_, buffer) = self.aovs.iter().find(|(a, _)| *a == aov)?;
+        let values = match buffer {
+            AovBuffer::Sum(sums) => sums
+                .par_iter()
+                .zip(self.stats.par_iter())
+                .map(|(&v, stats)| match aov {
+                    Aov::Normal if v.squared_len() > 0.0 => v.as_unit(),
+                    _ => v / stats.count.max(1) as f32,
+                })
+                .collect(),
+            AovBuffer::Key(keys) => {
+                // number materials densely, in the order they are seen
+                let mut ids = HashMap::new();
+                keys.iter()
+                    .map(|&key| {
+                        let id = match (aov, key) {
+                            (_, 0) => 0,
+                            (Aov::MaterialId, _) => {
+                                let next = ids.len() + 1;
+                                *ids.entry(key).or_insert(next)
+                            }
+                            _ => key,
+                        };
+                        vec3![id as f32, id as f32, id as f32]
+                    })
+                    .collect()
+            }
+        };
+        Some(values)
+    }
+
+    /// The filtered linear radiance, denoised if a denoiser is set.
+    pub fn image(&self) -> Vec<Vec3> {
+        let radiance = self.radiance();
+        match &self.denoiser {
+            Some(options) => denoise(
+                &radiance,
+                &self.features(),
+                self.width,
+                self.height,
+                options,
+            ),
+            None => radiance,
+        }
+    }
+
+    /// Encode the current image into an ARGB display buffer through the
+    /// output transform.
+    pub fn to_argb(&self, buffer: &mut [u32]) {
+        if buffer.len() < self.width * self.height {
+            panic!("display buffer is insufficiently sized");
+        }
+        let output = &self.output;
+        buffer
+            .par_iter_mut()
+            .zip(self.image().par_iter())
+            .for_each(|(argb, &v)| *argb = output.to_argb(v));
+    }
+
+    /// Draw the samples each pixel has received into an ARGB display buffer,
+    /// from dark blue for the fewest to yellow for the most.
+    pub fn heat_map_argb(&self, buffer: &mut [u32]) {
+        if buffer.len() < self.width * self.height {
+            panic!("display buffer is insufficiently sized");
+        }
+        let max = self.stats.par_iter().map(|s| s.count).max().unwrap_or(0);
+        let scale = 1.0 / max.max(1) as f32;
+        buffer
+            .par_iter_mut()
+            .zip(self.stats.par_iter())
+            .for_each(|(argb, s)| *argb = heat_color(s.count as f32 * scale).to_argb());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{FilterKind, Lambertian, Sphere};
+
+    #[test]
+    fn converged_pixels_stop_receiving_samples() {
+        let (width, height) = (24, 12);
+        let camera = ThinLensCamera::new(
+            Vec3::zeros(),
+            vec3![0, 0, -1],
+            vec3![0, 1, 0],
+            90.0,
+            2.0,
+            0.0,
+            1.0,
+            0.0,
+            1.0,
+        );
+        // a noisy diffuse sphere on the left, and plain sky everywhere else
+        let world = vec![
+            Sphere::new(vec3![-2, 0, -2], 1.0, Lambertian::new(vec3![0.5, 0.5, 0.5])).into_box(),
+        ];
+        let sky = vec3![0.5, 0.7, 1.0];
+        let mut tracer = Tracer::new(camera, world, move |_: &Ray| sky, width, height);
+        tracer.set_filter(Filter::new(FilterKind::Tent, 1.5));
+        let options = AdaptiveOptions {
+            threshold: 1e-6,
+            min_samples: 4,
+        };
+        tracer.set_adaptive(Some(options));
+        for _ in 0..options.min_samples {
+            tracer.render_sample();
+        }
+        let converged = tracer.radiance();
+        for _ in 0..8 {
+            tracer.render_sample();
+        }
+
+        let counts = tracer.sample_counts();
+        let radiance = tracer.radiance();
+        // the right half only ever sees the sky
+        let right = (0..height).map(|y| y * width + width - 1);
+        for p in right {
+            assert_eq!(counts[p], options.min_samples);
+            assert!((radiance[p] - sky).len() < 1e-5);
+        }
+        assert!(counts.iter().any(|&c| c == options.min_samples + 8));
+        assert!(tracer.n_active() > 0 && tracer.n_active() < width * height);
+        for p in 0..width * height {
+            if counts[p] == options.min_samples {
+                assert_eq!(radiance[p], converged[p]);
+            }
+        }
     }
 }
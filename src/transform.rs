@@ -0,0 +1,80 @@
+use crate::prelude::{vec3, HitRecord, Hittable, Mat4, Ray, Vec3, AABB};
+
+/// An instance of `object` placed in the world by an affine matrix.
+pub struct Transform {
+    object: Box<dyn Hittable + Sync>,
+    matrix: Mat4,
+    inverse: Mat4,
+    // transposed inverse, for carrying normals into world space
+    normal_matrix: Mat4,
+}
+
+impl Transform {
+    pub fn new(object: Box<dyn Hittable + Sync>, matrix: Mat4) -> Self {
+        let inverse = matrix.inverse().expect("singular transform matrix");
+        Transform {
+            object,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+        }
+    }
+
+    pub fn translate(object: Box<dyn Hittable + Sync>, offset: Vec3) -> Self {
+        Transform::new(object, Mat4::translate(offset))
+    }
+
+    pub fn rotate_x(object: Box<dyn Hittable + Sync>, degrees: f32) -> Self {
+        Transform::new(object, Mat4::rotate_x(degrees))
+    }
+
+    pub fn rotate_y(object: Box<dyn Hittable + Sync>, degrees: f32) -> Self {
+        Transform::new(object, Mat4::rotate_y(degrees))
+    }
+
+    pub fn rotate_z(object: Box<dyn Hittable + Sync>, degrees: f32) -> Self {
+        Transform::new(object, Mat4::rotate_z(degrees))
+    }
+
+    /// Scale about the origin; unequal factors turn a `Sphere` into an ellipsoid.
+    pub fn scale(object: Box<dyn Hittable + Sync>, factors: Vec3) -> Self {
+        Transform::new(object, Mat4::scale(factors))
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, r: &Ray, tmin: f32, tmax: f32) -> Option<HitRecord<'_>> {
+        // the direction is not renormalized, so t is the same in both spaces
+        let local = Ray::new(
+            self.inverse.transform_point(r.origin),
+            self.inverse.transform_vector(r.direction),
+            r.time,
+        );
+        let mut hit = self.object.hit(&local, tmin, tmax)?;
+        hit.p = self.matrix.transform_point(hit.p);
+        hit.normal = self.normal_matrix.transform_vector(hit.normal).as_unit();
+        hit.velocity = self.matrix.transform_vector(hit.velocity);
+        Some(hit)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        let bbox = self.object.bounding_box(t0, t1)?;
+        let mut min = vec3![f32::MAX, f32::MAX, f32::MAX];
+        let mut max = vec3![f32::MIN, f32::MIN, f32::MIN];
+        for i in 0..8 {
+            let corner = vec3![
+                if i & 1 == 0 { bbox.min.x } else { bbox.max.x },
+                if i & 2 == 0 { bbox.min.y } else { bbox.max.y },
+                if i & 4 == 0 { bbox.min.z } else { bbox.max.z }
+            ];
+            let p = self.matrix.transform_point(corner);
+            min = vec3![min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)];
+            max = vec3![max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)];
+        }
+        Some(AABB::new(min, max))
+    }
+
+    fn into_box(self) -> Box<dyn Hittable + Sync> {
+        Box::new(self) as Box<dyn Hittable + Sync>
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use crate::prelude::{vec3, HitRecord, Hittable, Mat4, Material, Ray, Vec3, AABB};
+
+/// One triangle of a `TriangleMesh`, as indices into the mesh's buffers.
+#[derive(Clone, Copy, Debug)]
+pub struct MeshFace {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+    pub material: usize,
+}
+
+/// Vertex buffers shared by every triangle of a mesh.
+pub struct TriangleMesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f32, f32)>,
+    pub faces: Vec<MeshFace>,
+    pub materials: Vec<Box<dyn Material>>,
+}
+
+impl TriangleMesh {
+    /// A mesh of a single material from positions and triangle indices.
+    pub fn new(
+        positions: Vec<Vec3>,
+        indices: Vec<[usize; 3]>,
+        material: Box<dyn Material>,
+    ) -> Self {
+        let faces = indices
+            .into_iter()
+            .map(|positions| MeshFace {
+                positions,
+                normals: None,
+                uvs: None,
+                material: 0,
+            })
+            .collect();
+        TriangleMesh {
+            positions,
+            normals: vec![],
+            uvs: vec![],
+            faces,
+            materials: vec![material],
+        }
+    }
+
+    /// Replace every material of the mesh with `material`.
+    pub fn with_material(mut self, material: Box<dyn Material>) -> Self {
+        self.materials = vec![material];
+        self.faces.iter_mut().for_each(|f| f.material = 0);
+        self
+    }
+
+    /// Move the mesh's vertices and normals by an invertible affine `matrix`,
+    /// once, rather than wrapping every triangle in a `Transform`.
+    pub fn transformed(mut self, matrix: &Mat4) -> Self {
+        let normal_matrix = matrix
+            .inverse()
+            .expect("singular transform matrix")
+            .transpose();
+        for p in self.positions.iter_mut() {
+            *p = matrix.transform_point(*p);
+        }
+        for n in self.normals.iter_mut() {
+            *n = normal_matrix.transform_vector(*n);
+        }
+        // a mirroring matrix reverses the winding, and with it the geometric
+        // normal, so reverse it back
+        let [x, y, z] = [vec3![1, 0, 0], vec3![0, 1, 0], vec3![0, 0, 1]]
+            .map(|axis| matrix.transform_vector(axis));
+        if x.cross(&y).dot(&z) < 0.0 {
+            for face in self.faces.iter_mut() {
+                face.positions.swap(1, 2);
+                if let Some(normals) = face.normals.as_mut() {
+                    normals.swap(1, 2);
+                }
+                if let Some(uvs) = face.uvs.as_mut() {
+                    uvs.swap(1, 2);
+                }
+            }
+        }
+        self
+    }
+
+    /// Split the mesh into one hittable per face, suitable for the BVH.
+    pub fn into_triangles(self) -> Vec<Box<dyn Hittable + Sync>> {
+        let n_faces = self.faces.len();
+        let mesh = Arc::new(self);
+        (0..n_faces)
+            .map(|face| {
+                Triangle {
+                    mesh: mesh.clone(),
+                    face,
+                }
+                .into_box()
+            })
+            .collect()
+    }
+}
+
+pub struct Triangle {
+    mesh: Arc<TriangleMesh>,
+    face: usize,
+}
+
+impl Triangle {
+    fn face(&self) -> &MeshFace {
+        &self.mesh.faces[self.face]
+    }
+
+    fn vertices(&self) -> [Vec3; 3] {
+        let [a, b, c] = self.face().positions;
+        [
+            self.mesh.positions[a],
+            self.mesh.positions[b],
+            self.mesh.positions[c],
+        ]
+    }
+}
+
+fn max_dimension(v: Vec3) -> usize {
+    let (x, y, z) = (v.x.abs(), v.y.abs(), v.z.abs());
+    if x > y {
+        if x > z {
+            0
+        } else {
+            2
+        }
+    } else if y > z {
+        1
+    } else {
+        2
+    }
+}
+
+/// Woop, Benthin and Wald's watertight ray-triangle test, returning the ray
+/// parameter and the barycentric weights of each vertex.
+fn intersect(r: &Ray, [v0, v1, v2]: [Vec3; 3], tmin: f32, tmax: f32) -> Option<(f32, [f32; 3])> {
+    // permute axes so that the ray travels mostly along z
+    let kz = max_dimension(r.direction);
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    if r.direction[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+    let sx = r.direction[kx] / r.direction[kz];
+    let sy = r.direction[ky] / r.direction[kz];
+    let sz = 1.0 / r.direction[kz];
+
+    // shear the vertices into ray space
+    let a = v0 - r.origin;
+    let b = v1 - r.origin;
+    let c = v2 - r.origin;
+    let (ax, ay) = (a[kx] - sx * a[kz], a[ky] - sy * a[kz]);
+    let (bx, by) = (b[kx] - sx * b[kz], b[ky] - sy * b[kz]);
+    let (cx, cy) = (c[kx] - sx * c[kz], c[ky] - sy * c[kz]);
+
+    let mut u = cx * by - cy * bx;
+    let mut v = ax * cy - ay * cx;
+    let mut w = bx * ay - by * ax;
+    // recompute edge functions exactly on the boundary so that shared edges
+    // are never missed by both neighbors
+    if u == 0.0 || v == 0.0 || w == 0.0 {
+        u = (cx as f64 * by as f64 - cy as f64 * bx as f64) as f32;
+        v = (ax as f64 * cy as f64 - ay as f64 * cx as f64) as f32;
+        w = (bx as f64 * ay as f64 - by as f64 * ax as f64) as f32;
+    }
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+
+    let t = (u * sz * a[kz] + v * sz * b[kz] + w * sz * c[kz]) / det;
+    if t <= tmin || t >= tmax {
+        return None;
+    }
+    Some((t, [u / det, v / det, w / det]))
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, tmin: f32, tmax: f32) -> Option<HitRecord<'_>> {
+        let [v0, v1, v2] = self.vertices();
+        let (t, [b0, b1, b2]) = intersect(r, [v0, v1, v2], tmin, tmax)?;
+        let face = self.face();
+        let p = b0 * v0 + b1 * v1 + b2 * v2;
+        let geometric = (v1 - v0).cross(&(v2 - v0)).as_unit();
+        let normal = match face.normals {
+            Some([n0, n1, n2]) => {
+                let normals = &self.mesh.normals;
+                let shading = (b0 * normals[n0] + b1 * normals[n1] + b2 * normals[n2]).as_unit();
+                // keep the shading normal on the geometric normal's side
+                if shading.dot(&geometric) < 0.0 {
+                    -shading
+                } else {
+                    shading
+                }
+            }
+            None => geometric,
+        };
+        let (u, v) = match face.uvs {
+            Some([t0, t1, t2]) => {
+                let uvs = &self.mesh.uvs;
+                (
+                    b0 * uvs[t0].0 + b1 * uvs[t1].0 + b2 * uvs[t2].0,
+                    b0 * uvs[t0].1 + b1 * uvs[t1].1 + b2 * uvs[t2].1,
+                )
+            }
+            None => (b1, b2),
+        };
+        let material = self.mesh.materials[face.material].as_ref();
+        Some(HitRecord::new(t, p, normal, material).with_uv(u, v))
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        let [v0, v1, v2] = self.vertices();
+        let min = vec3![
+            v0.x.min(v1.x).min(v2.x),
+            v0.y.min(v1.y).min(v2.y),
+            v0.z.min(v1.z).min(v2.z)
+        ];
+        let max = vec3![
+            v0.x.max(v1.x).max(v2.x),
+            v0.y.max(v1.y).max(v2.y),
+            v0.z.max(v1.z).max(v2.z)
+        ];
+        Some(AABB::new(min, max).padded(1e-4))
+    }
+
+    fn into_box(self) -> Box<dyn Hittable + Sync> {
+        Box::new(self) as Box<dyn Hittable + Sync>
+    }
+}